@@ -10,6 +10,7 @@ use tablegen::{
     magics::{BISHOP_MAGICS, ROOK_MAGICS, SLIDER_ATTACK_TABLE_SIZE},
     pawn_move::generate_pawn_attacks,
     rays_between::generate_squares_between,
+    reductions::generate_lmr_reductions,
     slider_move::generate_slider_attacks,
     zobrist::{GeneratedKeys, generate_keys},
 };
@@ -215,5 +216,11 @@ pub struct Magic {{
 
     write_variable(&mut writer, "ROOK_MAGICS", ROOK_MAGICS)?;
     write_variable(&mut writer, "BISHOP_MAGICS", BISHOP_MAGICS)?;
+
+    let dest_path = Path::new(&out_dir).join("reductions.rs");
+    let tables = File::create(&dest_path)?;
+    let mut writer = BufWriter::new(tables);
+
+    write_variable(&mut writer, "LMR_REDUCTIONS", generate_lmr_reductions())?;
     Ok(())
 }
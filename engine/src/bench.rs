@@ -1,49 +1,18 @@
 use std::{
     str::FromStr,
-    sync::{
-        Arc, Condvar, Mutex,
-        atomic::{AtomicBool, Ordering},
-        mpsc::channel,
-    },
+    sync::{atomic::Ordering, mpsc::channel},
     time::Instant,
 };
 
-use engine::{
-    board::Board,
-    clock::Clock,
-    evaluation::Evaluation,
-    pv::PrincipleVariation,
-    search::{NodeCountBuffer, RootMove, Search, ThreadData},
-    threadpool::StopSync,
-    transposition::MaybeUninitTT,
-    types::search_limits::{SearchLimits, TimeLimit},
-};
+use engine::{board::Board, stats::StatsBuffer};
+
+use crate::search_setup::{fixed_depth_search, single_thread_data};
 
 pub(crate) fn bench(max_depth: u8) {
     let (engine_tx, engine_rx) = channel();
 
-    // Use 16 MB as default size
-    let tt = MaybeUninitTT::new(16);
-    let tt = unsafe {
-        tt.clear_chunk(0, 1);
-        tt.assume_init()
-    };
-
-    let stop_sync = Arc::new(StopSync {
-        stop: AtomicBool::new(false),
-        wait_for_stop: Mutex::new(false),
-        cond_var: Condvar::new(),
-    });
-
-    let nodes_buffer = Arc::new(NodeCountBuffer::new(1));
-
-    let mut td = ThreadData {
-        engine_tx,
-        tt,
-        stop_sync,
-        nodes_buffer: nodes_buffer.clone(),
-        thread_id: 0,
-    };
+    let mut td = single_thread_data(engine_tx);
+    let nodes_buffer = td.nodes_buffer.clone();
 
     let mut total_nodes = 0u64;
 
@@ -51,34 +20,14 @@ pub(crate) fn bench(max_depth: u8) {
 
     for fen in POSITIONS {
         // ignore engine messages
-        while let Ok(_) = engine_rx.try_recv() {}
+        while engine_rx.try_recv().is_ok() {}
 
         let board = Board::from_str(fen).unwrap();
-        let limits = SearchLimits {
-            time: TimeLimit::Infinite,
-            depth: Some(max_depth),
-            ..Default::default()
-        };
-
-        let clock = Clock::new(
-            Instant::now(),
-            &limits.time,
-            board.game_ply(),
-            board.side_to_move(),
-        );
-
-        let root_moves: Vec<RootMove> = board
-            .generate_moves()
-            .into_iter()
-            .map(|m| RootMove {
-                score: Evaluation::MIN,
-                pv: PrincipleVariation::from_root(m),
-            })
-            .collect();
 
         nodes_buffer.clear();
+        td.stop_sync.stop.store(false, Ordering::SeqCst);
 
-        let mut search = Search::new(board, limits, clock, root_moves, 1);
+        let mut search = fixed_depth_search(board, max_depth);
         search.iterative_deepening(&mut td, true);
 
         let nodes = nodes_buffer.get(0).load(Ordering::SeqCst);
@@ -88,6 +37,10 @@ pub(crate) fn bench(max_depth: u8) {
     let nps = total_nodes as f64 / now.elapsed().as_secs_f64();
 
     println!("{total_nodes} nodes {nps:.0} nps");
+
+    if StatsBuffer::enabled() {
+        println!("{}", td.stats.accumulate().summary());
+    }
 }
 
 /// Positions from AndyGrant's Ethereal
@@ -1,7 +1,8 @@
 use crate::movegen::attacks::{
-    between, bishop_attacks, knight_attacks, pawn_attacks, rook_attacks, slider_horizontal,
+    between, bishop_attacks, king_attacks, knight_attacks, pawn_attacks, rook_attacks,
+    slider_horizontal,
 };
-use crate::movegen::{MoveList, generate_moves};
+use crate::movegen::{MoveList, generate_moves, generate_quiet_moves};
 use crate::types::bitboard::BitBoard;
 use crate::types::castling_rights::{CastlingRights, UPDATE_CASTLING_RIGHT_TABLE};
 use crate::types::chess_move::{Move, MoveFlag};
@@ -17,6 +18,8 @@ use std::str::FromStr;
 #[derive(Debug, Clone)]
 pub struct BoardState {
     hash: u64,
+    /// Zobrist hash of the pawns only
+    pawn_hash: u64,
     en_passant_target: Option<Square>,
     castling_rights: CastlingRights,
     rule50: u8,
@@ -75,6 +78,9 @@ impl Board {
 
         self.remove_piece(from, source_piece);
         new_state.hash ^= zobrist::piece_keys(self.side_to_move, source_piece.ty(), from);
+        if source_piece.ty() == PieceType::Pawn {
+            new_state.pawn_hash ^= zobrist::piece_keys(self.side_to_move, PieceType::Pawn, from);
+        }
 
         let mut capture_target: Option<(Square, Piece)> = None;
         let mut ep_target = None;
@@ -86,15 +92,8 @@ impl Board {
                 ep_target = Some(to.forward(!self.side_to_move));
             }
             MoveFlag::Castling => {
-                const CASTLE_CONFIG: [(File, File); 2] = [(File::A, File::D), (File::H, File::F)];
-
-                let backrank = self.side_to_move.backrank();
-                let target_file = to.file();
-                let (rook_start_file, rook_end_file) = CASTLE_CONFIG[target_file as usize / 4];
-                let (rook_start_square, rook_end_square) = (
-                    Square::from(backrank, rook_start_file),
-                    Square::from(backrank, rook_end_file),
-                );
+                let (rook_start_square, rook_end_square) =
+                    castling_rook_squares(to, self.side_to_move);
 
                 let rook_piece = PieceType::Rook.to_piece(self.side_to_move);
 
@@ -148,6 +147,10 @@ impl Board {
             new_state.captured_piece = Some(piece);
             self.remove_piece(square, piece);
             new_state.hash ^= zobrist::piece_keys(!self.side_to_move, piece.ty(), square);
+            if piece.ty() == PieceType::Pawn {
+                new_state.pawn_hash ^=
+                    zobrist::piece_keys(!self.side_to_move, PieceType::Pawn, square);
+            }
 
             // remove castling right for that side
             if piece.ty() == PieceType::Rook {
@@ -166,6 +169,7 @@ impl Board {
         new_state.hash ^= zobrist::piece_keys(self.side_to_move, source_piece.ty(), to);
 
         if source_piece.ty() == PieceType::Pawn {
+            new_state.pawn_hash ^= zobrist::piece_keys(self.side_to_move, PieceType::Pawn, to);
             new_state.rule50 = 0;
         }
 
@@ -287,6 +291,8 @@ impl Board {
         assert!(self.checkers().is_empty());
 
         let mut new_state = self.state.clone();
+        new_state.last_move = None;
+        new_state.captured_piece = None;
         if let Some(target) = self.state.en_passant_target {
             new_state.en_passant_target = None;
             new_state.hash ^= zobrist::en_passant_keys(target.file());
@@ -463,6 +469,10 @@ impl Board {
         generate_moves::<true>(self)
     }
 
+    pub fn generate_quiet_moves(&self) -> MoveList {
+        generate_quiet_moves(self)
+    }
+
     pub fn is_repetition(&self) -> bool {
         self.history
             .iter()
@@ -513,6 +523,78 @@ impl Board {
         self.state.en_passant_target
     }
 
+    /// Returns the pieces of both colors attacking `square` given the `occupancy` as blockers.
+    ///
+    /// Pieces outside of `occupancy` are not removed from the result.
+    pub fn attackers_to(&self, square: Square, occupancy: BitBoard) -> BitBoard {
+        let pawns = self.pieces[PieceType::Pawn];
+        let bishops = self.pieces[PieceType::Bishop] | self.pieces[PieceType::Queen];
+        let rooks = self.pieces[PieceType::Rook] | self.pieces[PieceType::Queen];
+
+        (pawn_attacks(square, Color::White) & pawns & self.occupancies[Color::Black])
+            | (pawn_attacks(square, Color::Black) & pawns & self.occupancies[Color::White])
+            | (knight_attacks(square) & self.pieces[PieceType::Knight])
+            | (king_attacks(square) & self.pieces[PieceType::King])
+            | (bishop_attacks(square, occupancy) & bishops)
+            | (rook_attacks(square, occupancy) & rooks)
+    }
+
+    /// Returns whether the quiet move `mv` gives check without applying it.
+    ///
+    /// Covers direct checks by the moved piece, including the rook when castling, and
+    /// discovered checks by sliders that were blocked by the moved piece.
+    pub fn gives_check(&self, mv: Move) -> bool {
+        debug_assert!(mv.is_quiet());
+
+        let us = self.side_to_move;
+        let king_square = (self.pieces[PieceType::King] & self.occupancies[!us]).bit_scan();
+
+        let (mut from, mut to) = (mv.from(), mv.to());
+        let mut vacated = BitBoard::from_square(from);
+        let mut occupancy = self.combined;
+        occupancy ^= from;
+        occupancy |= to;
+
+        // a king can not give check itself, but the rook it castles with can
+        if mv.flag() == MoveFlag::Castling {
+            (from, to) = castling_rook_squares(to, us);
+            vacated |= from;
+            occupancy ^= from;
+            occupancy |= to;
+        }
+
+        let direct = match self.piece_at(from).map(|piece| piece.ty()) {
+            Some(PieceType::Pawn) => pawn_attacks(to, us),
+            Some(PieceType::Knight) => knight_attacks(to),
+            Some(PieceType::Bishop) => bishop_attacks(to, occupancy),
+            Some(PieceType::Rook) => rook_attacks(to, occupancy),
+            Some(PieceType::Queen) => bishop_attacks(to, occupancy) | rook_attacks(to, occupancy),
+            Some(PieceType::King) | None => BitBoard::EMPTY,
+        };
+        if direct.contains(king_square) {
+            return true;
+        }
+
+        let ours = self.occupancies[us] & !vacated;
+        let bishops = (self.pieces[PieceType::Bishop] | self.pieces[PieceType::Queen]) & ours;
+        let rooks = (self.pieces[PieceType::Rook] | self.pieces[PieceType::Queen]) & ours;
+
+        !((bishop_attacks(king_square, occupancy) & bishops)
+            | (rook_attacks(king_square, occupancy) & rooks))
+            .is_empty()
+    }
+
+    /// Returns the last move played or `None` if the last move was a null move
+    pub fn last_move(&self) -> Option<Move> {
+        self.state.last_move
+    }
+
+    /// Returns whether `color` has any pieces left besides pawns and the king
+    pub fn has_non_pawn_material(&self, color: Color) -> bool {
+        let pawns_and_kings = self.pieces[PieceType::Pawn] | self.pieces[PieceType::King];
+        !(self.occupancies[color] & !pawns_and_kings).is_empty()
+    }
+
     pub fn is_draw_by_fifty_move_rule(&self) -> bool {
         self.state.rule50 >= 100
     }
@@ -521,6 +603,25 @@ impl Board {
     pub fn hash(&self) -> u64 {
         self.state.hash
     }
+
+    /// Zobrist hash of the pawn structure, independent of all other pieces and the side to move
+    #[inline]
+    pub fn pawn_hash(&self) -> u64 {
+        self.state.pawn_hash
+    }
+}
+
+/// Start and end square of the rook when `color` castles with the king moving to `king_to`
+fn castling_rook_squares(king_to: Square, color: Color) -> (Square, Square) {
+    const CASTLE_CONFIG: [(File, File); 2] = [(File::A, File::D), (File::H, File::F)];
+
+    let backrank = color.backrank();
+    let (rook_start_file, rook_end_file) = CASTLE_CONFIG[king_to.file() as usize / 4];
+
+    (
+        Square::from(backrank, rook_start_file),
+        Square::from(backrank, rook_end_file),
+    )
 }
 
 impl fmt::Display for Board {
@@ -582,6 +683,7 @@ impl FromStr for Board {
         let mut mailbox = PerSquare::<Option<Piece>>::default();
 
         let mut hash = 0;
+        let mut pawn_hash = 0;
 
         let piece_placement_data = parts
             .next()
@@ -607,6 +709,9 @@ impl FromStr for Board {
                     mailbox[square] = Some(piece);
 
                     hash ^= zobrist::piece_keys(color, piece_type, square);
+                    if piece_type == PieceType::Pawn {
+                        pawn_hash ^= zobrist::piece_keys(color, piece_type, square);
+                    }
 
                     file += 1;
                     if file > 8 {
@@ -706,6 +811,7 @@ impl FromStr for Board {
             mailbox,
             state: BoardState {
                 hash,
+                pawn_hash,
                 en_passant_target,
                 castling_rights,
                 rule50: halfmove_clock,
@@ -798,4 +904,96 @@ Hash: 	0x63db9ee8e39032b7
         println!("{board}, {clone}");
         assert_eq!(board, clone);
     }
+
+    #[test]
+    fn test_attackers_to() {
+        use crate::types::bitboard::BitBoard;
+        use crate::types::square::Square::*;
+
+        let board = Board::from_str("1k2r3/8/3p4/4p3/8/2B5/4R3/K3R3 w - - 0 1").unwrap();
+
+        let mut expected = BitBoard::EMPTY;
+        expected |= D6;
+        expected |= C3;
+        expected |= E2;
+        expected |= E8;
+        assert_eq!(board.attackers_to(E5, board.combined()), expected);
+
+        // removing the front rook reveals the rook behind it
+        let mut occupancy = board.combined();
+        occupancy ^= E2;
+        expected |= E1;
+        assert_eq!(board.attackers_to(E5, occupancy), expected);
+    }
+
+    #[test]
+    fn test_gives_check() {
+        let positions = [
+            // castling checks with the rook
+            "3k4/8/8/8/8/8/8/R3K2R w KQ - 0 1",
+            "r3k2r/8/8/8/8/8/8/3K4 b kq - 0 1",
+            // discovered checks by the rook and direct checks by the knight
+            "4k3/8/8/8/4N3/8/8/4R1K1 w - - 0 1",
+            // discovered checks by the bishop through a pawn push
+            "7k/8/8/8/8/2P5/1B6/K7 w - - 0 1",
+            "4k3/8/3P4/8/8/8/8/4K3 w - - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        ];
+
+        for fen in positions {
+            let mut board = Board::from_str(fen).unwrap();
+            for mv in board.generate_moves() {
+                if !mv.is_quiet() {
+                    continue;
+                }
+
+                let gives_check = board.gives_check(mv);
+                board.apply_move(mv);
+                assert_eq!(gives_check, !board.checkers().is_empty(), "{fen} {mv}");
+                board.undo_move();
+            }
+        }
+    }
+
+    #[test]
+    fn test_has_non_pawn_material() {
+        use crate::types::color::Color;
+
+        let board = Board::from_str("4k3/pppp4/8/8/8/8/4PPPP/2R1K3 w - - 0 1").unwrap();
+        assert!(board.has_non_pawn_material(Color::White));
+        assert!(!board.has_non_pawn_material(Color::Black));
+    }
+
+    #[test]
+    fn test_pawn_hash() {
+        use crate::types::color::Color;
+        use crate::types::piece::PieceType;
+        use crate::zobrist;
+
+        fn recompute(board: &Board) -> u64 {
+            let mut hash = 0;
+            for color in [Color::White, Color::Black] {
+                for square in board.pieces(PieceType::Pawn) & board.occupancies(color) {
+                    hash ^= zobrist::piece_keys(color, PieceType::Pawn, square);
+                }
+            }
+            hash
+        }
+
+        // en passant, promotions, promotion captures and pawn captures are all available
+        let mut board = Board::from_str("r3k3/1P6/8/3pP3/8/8/8/4K2R w K d6 0 1").unwrap();
+        let original = board.pawn_hash();
+        assert_eq!(original, recompute(&board));
+
+        for mv in board.generate_moves() {
+            board.apply_move(mv);
+            assert_eq!(board.pawn_hash(), recompute(&board), "{mv}");
+            board.undo_move();
+            assert_eq!(board.pawn_hash(), original);
+        }
+
+        // the pawn hash ignores all other pieces
+        let other = Board::from_str("4k3/1P6/8/3pP3/8/8/8/R3K3 b - - 0 1").unwrap();
+        assert_eq!(other.pawn_hash(), original);
+    }
 }
@@ -2,25 +2,67 @@ use crate::types::color::Color;
 use crate::types::search_limits::TimeLimit;
 use web_time::{Duration, Instant};
 
+/// Time management settings configurable through UCI options
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeOptions {
+    /// Time in milliseconds reserved per move for communication delays
+    pub move_overhead: u64,
+    /// Percentage by which the time spent per move is scaled
+    pub slow_mover: u64,
+    /// Lower bound in milliseconds for the optimum time of a move
+    pub minimum_thinking_time: u64,
+    /// Interpret the clock as a node budget of this many nodes per millisecond. Zero disables
+    /// this mode.
+    pub nodes_time: u64,
+}
+
+impl TimeOptions {
+    pub const DEFAULT_MOVE_OVERHEAD: u64 = 50;
+    pub const DEFAULT_SLOW_MOVER: u64 = 100;
+    pub const DEFAULT_MINIMUM_THINKING_TIME: u64 = 0;
+    pub const DEFAULT_NODES_TIME: u64 = 0;
+}
+
+impl Default for TimeOptions {
+    fn default() -> Self {
+        Self {
+            move_overhead: Self::DEFAULT_MOVE_OVERHEAD,
+            slow_mover: Self::DEFAULT_SLOW_MOVER,
+            minimum_thinking_time: Self::DEFAULT_MINIMUM_THINKING_TIME,
+            nodes_time: Self::DEFAULT_NODES_TIME,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Clock {
     pub start: Instant,
     pub maximum: Option<Instant>,
     pub optimum: Option<Instant>,
+    /// Nodes per millisecond if time is measured in searched nodes
+    nodes_time: Option<u64>,
 }
 
 impl Clock {
-    pub fn new(start: Instant, limits: &TimeLimit, game_ply: u16, color: Color) -> Self {
+    pub fn new(
+        start: Instant,
+        limits: &TimeLimit,
+        game_ply: u16,
+        color: Color,
+        options: &TimeOptions,
+    ) -> Self {
         match limits {
-            TimeLimit::Infinite | TimeLimit::External => Self {
+            TimeLimit::Infinite | TimeLimit::External | TimeLimit::Ponder { .. } => Self {
                 start,
                 maximum: None,
                 optimum: None,
+                nodes_time: None,
             },
             TimeLimit::Fixed { move_time } => Self {
                 start,
                 maximum: Some(start + *move_time),
                 optimum: Some(start + *move_time),
+                nodes_time: (options.nodes_time != 0).then_some(options.nodes_time),
             },
             TimeLimit::Dynamic {
                 time_left,
@@ -29,7 +71,6 @@ impl Clock {
             } => {
                 // Inspired by: https://github.com/official-stockfish/Stockfish/blob/65ece7d985291cc787d6c804a33f1dd82b75736d/src/timeman.cpp#L37
 
-                const MOVE_OVERHEAD: u64 = 50;
                 const MIN_TIME: u64 = 1;
                 const MIN_MTG: u64 = 50;
 
@@ -38,32 +79,82 @@ impl Clock {
                 let time_left = time_left[color].as_millis() as u64;
                 let inc = increment[color].as_millis() as u64;
 
-                let allocated_time = time_left / mtg + 3 * inc / 4;
+                // with nodes as time there is no communication delay to account for
+                let (nodes_time, move_overhead) = match options.nodes_time {
+                    0 => (None, options.move_overhead),
+                    nodes_time => (Some(nodes_time), 0),
+                };
+
+                let allocated_time = (time_left / mtg + 3 * inc / 4) * options.slow_mover / 100;
+
+                let available_time = time_left.saturating_sub(move_overhead).max(MIN_TIME);
 
-                let optimum_time = allocated_time.saturating_sub(MOVE_OVERHEAD).max(MIN_TIME);
+                let optimum_time = allocated_time
+                    .saturating_sub(move_overhead)
+                    .max(options.minimum_thinking_time)
+                    .clamp(MIN_TIME, available_time);
 
                 let max_scale = (4.0 + game_ply as f64 / 12.0).min(7.0);
 
-                let maximum_time = ((optimum_time as f64 * max_scale) as u64).clamp(
-                    MIN_TIME,
-                    time_left.saturating_sub(MOVE_OVERHEAD).max(MIN_TIME),
-                );
+                let maximum_time =
+                    ((optimum_time as f64 * max_scale) as u64).clamp(MIN_TIME, available_time);
 
                 Self {
                     start,
                     maximum: Some(start + Duration::from_millis(maximum_time)),
                     optimum: Some(start + Duration::from_millis(optimum_time)),
+                    nodes_time,
                 }
             }
         }
 
         // everything is in milliseconds
     }
+
+    /// The current point in time of this clock.
+    ///
+    /// When the clock measures time in nodes, the time is derived from the number of `nodes`
+    /// searched so far instead of the wall clock.
+    pub fn now(&self, nodes: u64) -> Instant {
+        match self.nodes_time {
+            Some(nodes_time) => self.start + Duration::from_millis(nodes / nodes_time),
+            None => Instant::now(),
+        }
+    }
+
+    /// The optimum time scaled by `scale`, never exceeding the maximum time
+    pub fn scaled_optimum(&self, scale: f64) -> Option<Instant> {
+        let optimum = self.optimum?;
+        let scaled = self.start + (optimum - self.start).mul_f64(scale);
+
+        Some(self.maximum.map_or(scaled, |maximum| scaled.min(maximum)))
+    }
+}
+
+/// Spend less time the more iterations in a row agreed on the best move
+pub fn stability_factor(best_move_stability: u8) -> f64 {
+    const FACTORS: [f64; 5] = [2.0, 1.2, 0.9, 0.8, 0.75];
+
+    FACTORS[(best_move_stability as usize).min(FACTORS.len() - 1)]
+}
+
+/// Spend more time when the score dropped compared to the previous iteration and less when it
+/// improved. `score_drop` is in centipawns.
+pub fn score_trend_factor(score_drop: i16) -> f64 {
+    (1.0 + score_drop as f64 * 0.005).clamp(0.8, 1.5)
+}
+
+/// Spend less time when the search of the best move took up most of the nodes, since the other
+/// moves were refuted quickly. `best_move_nodes` is the share of the nodes in `[0, 1]`.
+pub fn node_share_factor(best_move_nodes: f64) -> f64 {
+    (1.5 - best_move_nodes) * 1.35
 }
 
 #[cfg(test)]
 mod test {
-    use crate::clock::Clock;
+    use crate::clock::{
+        Clock, TimeOptions, node_share_factor, score_trend_factor, stability_factor,
+    };
     use crate::types::color::{Color, PerColor};
     use crate::types::search_limits::TimeLimit;
     use std::time::{Duration, Instant};
@@ -79,6 +170,7 @@ mod test {
             },
             0,
             Color::White,
+            &TimeOptions::default(),
         );
 
         println!("{clock:#?}");
@@ -91,4 +183,87 @@ mod test {
             (clock.maximum.unwrap() - clock.start)
         );
     }
+
+    #[test]
+    fn test_scaled_optimum_is_capped_by_maximum() {
+        let start = Instant::now();
+        let clock = Clock::new(
+            start,
+            &TimeLimit::Dynamic {
+                time_left: PerColor::new([Duration::from_secs(60); 2]),
+                increment: PerColor::new([Duration::ZERO; 2]),
+                moves_to_go: None,
+            },
+            0,
+            Color::White,
+            &TimeOptions::default(),
+        );
+
+        let optimum = clock.optimum.unwrap();
+        assert_eq!(clock.scaled_optimum(1.0), Some(optimum));
+        assert!(clock.scaled_optimum(0.5).unwrap() < optimum);
+        assert!(clock.scaled_optimum(2.0).unwrap() > optimum);
+        assert_eq!(clock.scaled_optimum(100.0), clock.maximum);
+
+        let infinite = Clock::new(
+            start,
+            &TimeLimit::Infinite,
+            0,
+            Color::White,
+            &TimeOptions::default(),
+        );
+        assert_eq!(infinite.scaled_optimum(1.0), None);
+    }
+
+    #[test]
+    fn test_time_factors() {
+        assert!(stability_factor(0) > stability_factor(1));
+        assert_eq!(stability_factor(4), stability_factor(u8::MAX));
+
+        assert!(score_trend_factor(50) > 1.0);
+        assert!(score_trend_factor(-50) < 1.0);
+        assert_eq!(score_trend_factor(i16::MAX), 1.5);
+
+        assert!(node_share_factor(0.9) < node_share_factor(0.3));
+    }
+
+    #[test]
+    fn test_time_options() {
+        let start = Instant::now();
+        let limits = TimeLimit::Dynamic {
+            time_left: PerColor::new([Duration::from_millis(1000); 2]),
+            increment: PerColor::new([Duration::ZERO; 2]),
+            moves_to_go: None,
+        };
+
+        let options = TimeOptions {
+            move_overhead: 400,
+            ..Default::default()
+        };
+        let clock = Clock::new(start, &limits, 0, Color::White, &options);
+        assert_eq!(clock.maximum, Some(start + Duration::from_millis(4)));
+
+        let options = TimeOptions {
+            minimum_thinking_time: 500,
+            ..Default::default()
+        };
+        let clock = Clock::new(start, &limits, 0, Color::White, &options);
+        assert_eq!(clock.optimum, Some(start + Duration::from_millis(500)));
+
+        let options = TimeOptions {
+            nodes_time: 10,
+            ..Default::default()
+        };
+        let clock = Clock::new(start, &limits, 0, Color::White, &options);
+        assert_eq!(clock.now(5000), start + Duration::from_millis(500));
+
+        // a fixed move time is converted to nodes as well
+        let fixed = TimeLimit::Fixed {
+            move_time: Duration::from_millis(100),
+        };
+        let clock = Clock::new(start, &fixed, 0, Color::White, &options);
+        assert_eq!(clock.now(500), start + Duration::from_millis(50));
+        assert!(clock.maximum.unwrap() > clock.now(999));
+        assert!(clock.maximum.unwrap() <= clock.now(1000));
+    }
 }
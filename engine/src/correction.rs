@@ -0,0 +1,136 @@
+use crate::board::Board;
+use crate::evaluation::Evaluation;
+use crate::types::color::NUM_COLORS;
+
+/// Number of pawn structure buckets per side to move
+const CORRECTION_HISTORY_SIZE: usize = 16384;
+
+/// Corrections are stored in fractions of a centipawn to keep small updates from rounding away
+const CORRECTION_GRAIN: i32 = 256;
+
+/// Upper bound of the absolute value of a stored correction, i.e. 64 centipawns
+const MAX_CORRECTION: i32 = 64 * CORRECTION_GRAIN;
+
+/// Sum of the weights of an entry and a new sample
+const CORRECTION_WEIGHT_SCALE: i32 = 256;
+
+/// Largest weight of a single sample, reached at depth 16
+const MAX_CORRECTION_WEIGHT: i32 = 16;
+
+/// Per thread history of the error of the static evaluation, indexed by side to move and pawn
+/// structure. Search results that contradict the static evaluation pull the entry towards the
+/// observed difference, which is then added to the static evaluation of later positions with
+/// the same pawn structure.
+pub struct CorrectionHistory {
+    table: [[i16; CORRECTION_HISTORY_SIZE]; NUM_COLORS],
+}
+
+impl Default for CorrectionHistory {
+    fn default() -> Self {
+        Self {
+            table: [[0; CORRECTION_HISTORY_SIZE]; NUM_COLORS],
+        }
+    }
+}
+
+impl CorrectionHistory {
+    pub fn clear(&mut self) {
+        self.table = [[0; CORRECTION_HISTORY_SIZE]; NUM_COLORS];
+    }
+
+    /// Correction in centipawns for the static evaluation of `board`
+    pub fn correction(&self, board: &Board) -> i16 {
+        (*self.entry(board) as i32 / CORRECTION_GRAIN) as i16
+    }
+
+    /// Adds the learned correction to the static evaluation `eval` of `board`
+    pub fn correct(&self, board: &Board, eval: Evaluation) -> Evaluation {
+        eval + self.correction(board)
+    }
+
+    /// Moves the entry of `board` towards the difference between the search result `score` and
+    /// the uncorrected static evaluation `eval`, weighted by the search depth
+    pub fn update(&mut self, board: &Board, depth: u8, eval: Evaluation, score: Evaluation) {
+        let target = (score.val() as i32 - eval.val() as i32) * CORRECTION_GRAIN;
+        let weight = (depth as i32 + 1).min(MAX_CORRECTION_WEIGHT);
+
+        let entry = self.entry_mut(board);
+        let value = *entry as i32;
+        let value = (value * (CORRECTION_WEIGHT_SCALE - weight) + target * weight)
+            / CORRECTION_WEIGHT_SCALE;
+        *entry = value.clamp(-MAX_CORRECTION, MAX_CORRECTION) as i16;
+    }
+
+    fn entry(&self, board: &Board) -> &i16 {
+        &self.table[board.side_to_move() as usize]
+            [board.pawn_hash() as usize % CORRECTION_HISTORY_SIZE]
+    }
+
+    fn entry_mut(&mut self, board: &Board) -> &mut i16 {
+        &mut self.table[board.side_to_move() as usize]
+            [board.pawn_hash() as usize % CORRECTION_HISTORY_SIZE]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::board::Board;
+    use crate::correction::{CORRECTION_GRAIN, CorrectionHistory, MAX_CORRECTION};
+    use crate::evaluation::Evaluation;
+
+    #[test]
+    fn test_correction_converges() {
+        let board = Board::default();
+        let mut correction = CorrectionHistory::default();
+        let eval = Evaluation::EQUALITY;
+
+        // deeper results carry more weight
+        correction.update(&board, 4, eval, eval + 400);
+        let shallow = correction.correction(&board);
+        correction.clear();
+        correction.update(&board, 12, eval, eval + 400);
+        assert!(0 < shallow && shallow < correction.correction(&board));
+
+        for _ in 0..200 {
+            correction.update(&board, 20, eval, eval + 40);
+        }
+        assert!((38..=40).contains(&correction.correction(&board)));
+        assert_eq!(
+            correction.correct(&board, eval),
+            eval + correction.correction(&board)
+        );
+
+        correction.clear();
+        assert_eq!(correction.correction(&board), 0);
+    }
+
+    #[test]
+    fn test_correction_depends_on_pawns_and_side() {
+        let board = Board::default();
+        let mut correction = CorrectionHistory::default();
+        let eval = Evaluation::EQUALITY;
+
+        for _ in 0..200 {
+            correction.update(&board, 20, eval, eval - 1000);
+        }
+        assert_eq!(
+            correction.correction(&board) as i32,
+            -MAX_CORRECTION / CORRECTION_GRAIN
+        );
+
+        // same pawns with other pieces still share the entry
+        let knights =
+            Board::from_str("r1bqkb1r/pppppppp/2n2n2/8/8/2N2N2/PPPPPPPP/R1BQKB1R w KQkq - 4 3")
+                .unwrap();
+        assert_eq!(
+            correction.correction(&knights),
+            correction.correction(&board)
+        );
+
+        let black =
+            Board::from_str("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1").unwrap();
+        assert_eq!(correction.correction(&black), 0);
+    }
+}
@@ -0,0 +1,99 @@
+use std::{fs::File, io::BufWriter, str::FromStr, sync::mpsc::channel};
+
+use engine::{board::Board, tree_dump::TreeDump};
+
+use crate::search_setup::{fixed_depth_search, single_thread_data};
+
+const USAGE: &str = "usage: engine dumptree <depth> <file[.dot]> [fen]";
+
+/// Entry point of the `dumptree` subcommand, which searches a single position to a fixed depth
+/// and writes every visited node to a file.
+///
+/// The tree is written as Graphviz DOT if the file name ends with `.dot` and as JSON Lines
+/// otherwise. The start position is used if no FEN is given.
+pub(crate) fn run(args: &[String]) {
+    if !TreeDump::enabled() {
+        eprintln!("the engine was built without the `tree_dump` feature");
+        return;
+    }
+
+    let (Some(depth), Some(path)) = (args.first(), args.get(1)) else {
+        eprintln!("{USAGE}");
+        return;
+    };
+
+    let Ok(depth) = depth.parse::<u8>() else {
+        eprintln!("invalid depth: {depth}\n{USAGE}");
+        return;
+    };
+
+    let board = if args.len() > 2 {
+        let fen = args[2..].join(" ");
+        match Board::from_str(&fen) {
+            Ok(board) => board,
+            Err(_) => {
+                eprintln!("invalid fen: {fen}");
+                return;
+            }
+        }
+    } else {
+        Board::default()
+    };
+
+    let tree_dump = search_tree(board, depth);
+
+    let result = File::create(path).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        if path.ends_with(".dot") {
+            tree_dump.write_dot(&mut out)
+        } else {
+            tree_dump.write_json_lines(&mut out)
+        }
+    });
+
+    match result {
+        Ok(()) => println!("wrote {} nodes to {path}", tree_dump.len()),
+        Err(err) => eprintln!("failed to write {path}: {err}"),
+    }
+}
+
+/// Searches `board` to `depth` while recording the tree. The tree is empty if the side to move
+/// has no legal moves.
+fn search_tree(board: Board, depth: u8) -> Box<TreeDump> {
+    let (engine_tx, _engine_rx) = channel();
+    let mut td = single_thread_data(engine_tx);
+
+    let mut search = fixed_depth_search(board, depth);
+    if search.root_moves.is_empty() {
+        let result = if search.board.checkers().is_empty() {
+            "stalemate"
+        } else {
+            "checkmate"
+        };
+        println!("the position is {result}, there is no tree to search");
+        return Box::default();
+    }
+
+    search.tree_dump = Some(Default::default());
+    search.iterative_deepening(&mut td, true);
+
+    search.tree_dump.take().unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_search_tree_without_legal_moves() {
+        for fen in [
+            // stalemate
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+            // checkmate
+            "7k/6Q1/6K1/8/8/8/8/8 b - - 0 1",
+        ] {
+            let board = Board::from_str(fen).unwrap();
+            assert!(search_tree(board, 3).is_empty());
+        }
+    }
+}
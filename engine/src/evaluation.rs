@@ -4,7 +4,7 @@ pub mod nnue;
 use crate::types::color::Color;
 use std::fmt;
 use std::fmt::Formatter;
-use std::ops::Neg;
+use std::ops::{Add, Neg, Sub};
 
 #[derive(PartialEq, Clone, Copy, Debug, PartialOrd, Ord, Eq)]
 pub struct Evaluation(i16);
@@ -69,6 +69,18 @@ impl Evaluation {
     pub const fn val(&self) -> i16 {
         self.0
     }
+
+    /// Adds `rhs` and clamps the result to the range of [`Evaluation::MIN`] and [`Evaluation::MAX`]
+    pub const fn saturating_add(self, rhs: i16) -> Evaluation {
+        let sum = self.0.saturating_add(rhs);
+        if sum < Evaluation::MIN.0 {
+            Evaluation::MIN
+        } else if sum > Evaluation::MAX.0 {
+            Evaluation::MAX
+        } else {
+            Evaluation(sum)
+        }
+    }
 }
 
 impl Neg for Evaluation {
@@ -80,6 +92,24 @@ impl Neg for Evaluation {
     }
 }
 
+impl Add<i16> for Evaluation {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: i16) -> Self::Output {
+        Evaluation(self.0 + rhs)
+    }
+}
+
+impl Sub<i16> for Evaluation {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: i16) -> Self::Output {
+        Evaluation(self.0 - rhs)
+    }
+}
+
 impl fmt::Display for Evaluation {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         if self.is_mate() {
@@ -171,6 +201,13 @@ mod test {
         assert_eq!(Evaluation::MAX, -Evaluation::MIN);
     }
 
+    #[test]
+    fn test_saturating_add() {
+        assert_eq!(Evaluation(10).saturating_add(-25), Evaluation(-15));
+        assert_eq!(Evaluation(32000).saturating_add(500), Evaluation::MAX);
+        assert_eq!(Evaluation(-32000).saturating_add(i16::MIN), Evaluation::MIN);
+    }
+
     #[test]
     fn test_mate_num_ply() {
         let evaluation = Evaluation(-Evaluation::IMMEDIATE_MATE_SCORE + 50);
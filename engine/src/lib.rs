@@ -1,11 +1,16 @@
 pub mod board;
 pub mod clock;
+pub mod correction;
 pub mod evaluation;
 pub mod movegen;
 pub mod moveord;
 pub mod search;
+pub mod see;
+pub mod skill;
+pub mod stats;
 pub mod threadpool;
 pub mod transposition;
+pub mod tree_dump;
 pub mod types;
 pub mod uci;
 pub mod pv;
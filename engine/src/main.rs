@@ -1,4 +1,6 @@
 mod bench;
+mod dumptree_command;
+mod search_setup;
 
 use std::{
     io::{BufRead, stdin},
@@ -29,9 +31,17 @@ impl Printer for StdoutPrinter {
 }
 
 fn main() {
-    if let Some("bench") = std::env::args().nth(1).as_deref() {
-        bench::bench(8);
-        return;
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("bench") => {
+            bench::bench(8);
+            return;
+        }
+        Some("dumptree") => {
+            dumptree_command::run(&args[2..]);
+            return;
+        }
+        _ => {}
     }
 
     let (engine_tx, engine_rx) = channel();
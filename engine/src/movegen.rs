@@ -1,5 +1,5 @@
 use arrayvec::ArrayVec;
-use castling::generate_castling_moves;
+use castling::{generate_castling_moves, is_castling_legal};
 use en_passant::generate_en_passant_move;
 use king::generate_king_moves;
 use knight::generate_knight_moves;
@@ -71,6 +71,140 @@ pub fn generate_moves<const CAPTURE_ONLY: bool>(board: &Board) -> MoveList {
     move_list
 }
 
+/// Generates the legal moves that do not capture, i.e. the moves missing from
+/// `generate_moves::<true>`.
+pub fn generate_quiet_moves(board: &Board) -> MoveList {
+    let mut move_list = MoveList::new();
+
+    let checkers = board.checkers();
+
+    let PushCaptureMasks {
+        push_mask,
+        king_push_mask,
+        attacked,
+        ..
+    } = compute_masks::<false>(board);
+
+    // only the king can move when in double check
+    if checkers.count() < 2 {
+        generate_quiet_pawn_moves(board, &mut move_list, push_mask);
+        // castling is not allowed when the king is in check
+        if checkers.is_empty() {
+            generate_castling_moves(board, &mut move_list, attacked);
+        }
+        generate_knight_moves(board, &mut move_list, BitBoard::EMPTY, push_mask);
+        generate_slider_moves(board, &mut move_list, BitBoard::EMPTY, push_mask);
+    }
+    generate_king_moves(board, &mut move_list, BitBoard::EMPTY, king_push_mask);
+
+    move_list
+}
+
+/// Returns whether `mv` is legal in the current position.
+///
+/// The move is checked directly against the attacks of the moving piece, pins and checks,
+/// which makes this a cheaper way of validating moves from other sources (e.g. the
+/// transposition table) than generating moves.
+pub fn is_legal(board: &Board, mv: Move) -> bool {
+    let side_to_move = board.side_to_move();
+    let (from, to, flag) = (mv.from(), mv.to(), mv.flag());
+
+    let Some(piece) = board.piece_at(from) else {
+        return false;
+    };
+
+    if piece.color() != side_to_move {
+        return false;
+    }
+
+    // en passant is the only capture that does not land on the captured piece
+    if flag == MoveFlag::EnPassant {
+        if piece.ty() != PieceType::Pawn {
+            return false;
+        }
+
+        // the captured pawn can uncover a check along the rank, which the generator handles
+        let mut move_list = MoveList::new();
+        generate_en_passant_move(board, &mut move_list);
+        return move_list.contains(&mv);
+    }
+
+    let target_mask = if mv.is_capture() {
+        board.occupancies(!side_to_move)
+    } else {
+        !board.combined()
+    };
+
+    if !target_mask.contains(to) {
+        return false;
+    }
+
+    let checkers = board.checkers();
+    let combined = board.combined();
+    let is_normal = matches!(flag, MoveFlag::Normal | MoveFlag::Capture);
+
+    let reachable = match piece.ty() {
+        PieceType::King if flag == MoveFlag::Castling => {
+            return checkers.is_empty() && is_castling_legal(board, mv);
+        }
+        PieceType::King => {
+            // the king does not block the attacks on the squares behind it
+            return is_normal
+                && king_attacks(from).contains(to)
+                && !sq_attacked_given_blockers(
+                    board,
+                    to,
+                    !side_to_move,
+                    combined ^ BitBoard::from_square(from),
+                );
+        }
+        // only the king can move when in double check
+        _ if checkers.count() > 1 => false,
+        PieceType::Pawn => {
+            let promotion_rank = side_to_move.promotion_rank().mask();
+
+            if promotion_rank.contains(to) != mv.promotion().is_some() {
+                false
+            } else if mv.is_capture() {
+                pawn_attacks(from, side_to_move).contains(to)
+            } else if flag == MoveFlag::DoublePawnPush {
+                to.rank() == side_to_move.double_pawn_push_rank() && {
+                    let skipped = to.forward(!side_to_move);
+                    skipped.forward(!side_to_move) == from && !combined.contains(skipped)
+                }
+            } else {
+                flag != MoveFlag::Castling && from.forward(side_to_move) == to
+            }
+        }
+        PieceType::Knight => is_normal && knight_attacks(from).contains(to),
+        PieceType::Bishop => is_normal && bishop_attacks(from, combined).contains(to),
+        PieceType::Rook => is_normal && rook_attacks(from, combined).contains(to),
+        PieceType::Queen => {
+            is_normal
+                && (bishop_attacks(from, combined) | rook_attacks(from, combined)).contains(to)
+        }
+    };
+
+    if !reachable {
+        return false;
+    }
+
+    let king_square = (board.pieces(PieceType::King) & board.occupancies(side_to_move)).bit_scan();
+
+    // a pinned piece can only move along the line through the king and the pinner
+    if board.pinned().contains(from) && !aligned(king_square, from, to) {
+        return false;
+    }
+
+    // a single check has to be evaded by capturing the checker or blocking the check
+    checkers.is_empty() || (checkers | between(king_square, checkers.bit_scan())).contains(to)
+}
+
+/// Returns whether the three distinct squares lie on a common rank, file or diagonal
+fn aligned(a: Square, b: Square, c: Square) -> bool {
+    between(a, b).contains(c) || between(a, c).contains(b) || between(b, c).contains(a)
+}
+
 pub trait MoveListExt {
     fn push_move(&mut self, from: Square, to: Square, flag: MoveFlag);
 }
@@ -266,10 +400,10 @@ mod test {
     use crate::board::Board;
     use crate::movegen::{
         MoveList, PushCaptureMasks, compute_masks, generate_attack_bitboard, generate_moves,
-        sq_attacked,
+        generate_quiet_moves, is_legal, sq_attacked,
     };
     use crate::types::bitboard::BitBoard;
-    use crate::types::chess_move::Move;
+    use crate::types::chess_move::{Move, MoveFlag};
     use crate::types::color::Color;
     use crate::types::square::Square;
 
@@ -398,6 +532,72 @@ mod test {
         assert_eq!(moves.len(), 46);
     }
 
+    const LEGALITY_FENS: [&str; 10] = [
+        Board::STARTING_POS_FEN,
+        Board::KILLER_POS_FEN,
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "4k3/8/8/8/8/8/4r3/R3K2R w KQ - 0 1",
+        // pins, en passant and promotions
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "8/8/8/2k5/3Pp3/8/8/4K2B b - d3 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        // single and double check
+        "4k3/8/8/8/1b6/8/3P4/r3K2N w - - 0 1",
+        "4k3/8/8/8/1b6/8/8/r3K2N w - - 0 1",
+    ];
+
+    const ALL_FLAGS: [MoveFlag; 13] = [
+        MoveFlag::Normal,
+        MoveFlag::DoublePawnPush,
+        MoveFlag::Castling,
+        MoveFlag::Capture,
+        MoveFlag::EnPassant,
+        MoveFlag::KnightPromotion,
+        MoveFlag::BishopPromotion,
+        MoveFlag::RookPromotion,
+        MoveFlag::QueenPromotion,
+        MoveFlag::KnightPromotionCapture,
+        MoveFlag::BishopPromotionCapture,
+        MoveFlag::RookPromotionCapture,
+        MoveFlag::QueenPromotionCapture,
+    ];
+
+    #[test]
+    fn legality_matches_generated_moves() {
+        for fen in LEGALITY_FENS {
+            let board = Board::from_str(fen).unwrap();
+            let legal_moves = generate_moves::<false>(&board);
+
+            for from in 0..64 {
+                for to in 0..64 {
+                    for flag in ALL_FLAGS {
+                        let mv = Move::new(Square::from_index(from), Square::from_index(to), flag);
+                        assert_eq!(is_legal(&board, mv), legal_moves.contains(&mv), "{mv:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn quiets_complement_captures() {
+        for fen in LEGALITY_FENS {
+            let board = Board::from_str(fen).unwrap();
+            let legal_moves = generate_moves::<false>(&board);
+            let captures = generate_moves::<true>(&board);
+            let quiets = generate_quiet_moves(&board);
+
+            assert!(quiets.iter().all(|mv| !mv.is_capture()), "{fen}");
+            assert_eq!(captures.len() + quiets.len(), legal_moves.len(), "{fen}");
+            assert!(
+                legal_moves
+                    .iter()
+                    .all(|mv| captures.contains(mv) || quiets.contains(mv))
+            );
+        }
+    }
+
     #[test]
     fn captures_only() {
         let board = Board::from_str(
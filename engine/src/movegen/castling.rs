@@ -1,6 +1,6 @@
 use crate::board::Board;
-use crate::movegen::MoveList;
 use crate::movegen::attacks::between;
+use crate::movegen::{MoveList, sq_attacked};
 use crate::types::bitboard::BitBoard;
 use crate::types::castling_rights::CastlingRights;
 use crate::types::chess_move::{Move, MoveFlag};
@@ -68,6 +68,28 @@ pub fn generate_castling_moves(board: &Board, move_list: &mut MoveList, attacked
     }
 }
 
+/// Returns whether the castling move `mv` is legal, given that the king is not in check.
+///
+/// Only the squares the king passes are tested for attacks instead of building the attack
+/// bitboard of the opponent.
+pub fn is_castling_legal(board: &Board, mv: Move) -> bool {
+    let side_to_move = board.side_to_move();
+    let king_square = (board.pieces(PieceType::King) & board.occupancies(side_to_move)).bit_scan();
+
+    mv.from() == king_square
+        && CASTLING_CONFIGS[side_to_move as usize]
+            .iter()
+            .any(|config| {
+                mv.to() == config.king_target
+                    && board.castling_rights().contains(config.required_rights)
+                    && (board.combined() & config.cleared_squares_bb) == BitBoard::EMPTY
+                    && config
+                        .safe_squares
+                        .into_iter()
+                        .all(|square| !sq_attacked(board, square, !side_to_move))
+            })
+}
+
 #[cfg(test)]
 mod test {
     use crate::board::Board;
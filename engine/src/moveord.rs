@@ -1,4 +1,12 @@
-use crate::types::piece::{NUM_PIECES, PieceType};
+use arrayvec::ArrayVec;
+
+use crate::board::Board;
+use crate::movegen::{MoveList, is_legal};
+use crate::see::see;
+use crate::types::chess_move::Move;
+use crate::types::color::NUM_COLORS;
+use crate::types::piece::{NUM_PIECES, Piece, PieceType};
+use crate::types::square::{NUM_SQUARES, Square};
 
 #[rustfmt::skip]
 const MVV_LVA: [[u8; NUM_PIECES]; NUM_PIECES] = [
@@ -14,10 +22,437 @@ pub(crate) fn mmv_lva(src_piece: PieceType, dst_piece: PieceType) -> i32 {
     MVV_LVA[dst_piece as usize][src_piece as usize] as i32
 }
 
+/// Upper bound of the absolute value of a history score
+const MAX_HISTORY: i32 = 16384;
+
+/// Ordering score of quiet promotions which are searched before any other quiet move
+const QUIET_PROMOTION_SCORE: i32 = 4 * MAX_HISTORY;
+
+/// Scale of the MVV-LVA score of captures. A single step in the victim value outweighs
+/// any capture history score.
+const CAPTURE_MVV_LVA_SCALE: i32 = MAX_HISTORY / 8;
+
+/// Number of distinct pieces of both colors
+const NUM_COLORED_PIECES: usize = NUM_COLORS * NUM_PIECES;
+
+/// A move identified by the moved piece and its target square, as used by the continuation
+/// history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PieceTo {
+    pub piece: Piece,
+    pub to: Square,
+}
+
+impl PieceTo {
+    fn index(self) -> usize {
+        let piece = self.piece.color() as usize * NUM_PIECES + self.piece.ty() as usize;
+        piece * NUM_SQUARES + self.to as usize
+    }
+}
+
+/// The moves one and two plies before the current move, `None` if there is no such move or
+/// it was a null move
+pub type Continuations = [Option<PieceTo>; 2];
+
+/// Per thread move ordering state that is kept between searches
+pub struct OrderingTables {
+    /// Butterfly history indexed by side to move, source and target square
+    history: [[[i16; NUM_SQUARES]; NUM_SQUARES]; NUM_COLORS],
+    /// Quiet refutation indexed by color, piece and target square of the previous move
+    counter_moves: [[[Move; NUM_SQUARES]; NUM_PIECES]; NUM_COLORS],
+    /// History of quiet moves following a previous move, both given as piece and target
+    /// square. Too large for the stack, so it is stored flat on the heap.
+    continuation_history: Vec<i16>,
+    /// History of captures indexed by color and type of the moving piece, target square and
+    /// captured piece type
+    capture_history: [[[[i16; NUM_PIECES]; NUM_SQUARES]; NUM_PIECES]; NUM_COLORS],
+}
+
+impl Default for OrderingTables {
+    fn default() -> Self {
+        Self {
+            history: [[[0; NUM_SQUARES]; NUM_SQUARES]; NUM_COLORS],
+            counter_moves: [[[Move::NULL; NUM_SQUARES]; NUM_PIECES]; NUM_COLORS],
+            continuation_history: vec![0; (NUM_COLORED_PIECES * NUM_SQUARES).pow(2)],
+            capture_history: [[[[0; NUM_PIECES]; NUM_SQUARES]; NUM_PIECES]; NUM_COLORS],
+        }
+    }
+}
+
+impl OrderingTables {
+    pub fn clear(&mut self) {
+        self.history = [[[0; NUM_SQUARES]; NUM_SQUARES]; NUM_COLORS];
+        self.counter_moves = [[[Move::NULL; NUM_SQUARES]; NUM_PIECES]; NUM_COLORS];
+        self.continuation_history.fill(0);
+        self.capture_history = [[[[0; NUM_PIECES]; NUM_SQUARES]; NUM_PIECES]; NUM_COLORS];
+    }
+
+    pub fn history(&self, board: &Board, mv: Move) -> i32 {
+        self.history[board.side_to_move() as usize][mv.from() as usize][mv.to() as usize] as i32
+    }
+
+    /// Sum of the continuation histories of the quiet move `mv` for each previous move
+    pub fn continuation_history(
+        &self,
+        board: &Board,
+        continuations: &Continuations,
+        mv: Move,
+    ) -> i32 {
+        let current = piece_to(board, mv);
+
+        continuations
+            .iter()
+            .flatten()
+            .map(|previous| {
+                self.continuation_history[continuation_index(*previous, current)] as i32
+            })
+            .sum()
+    }
+
+    pub fn capture_history(&self, board: &Board, mv: Move) -> i32 {
+        let piece = board.piece_at(mv.from()).unwrap();
+        let captured = captured_piece_type(board, mv);
+
+        self.capture_history[piece.color() as usize][piece.ty() as usize][mv.to() as usize]
+            [captured as usize] as i32
+    }
+
+    fn capture_history_entry_mut(&mut self, board: &Board, mv: Move) -> &mut i16 {
+        let piece = board.piece_at(mv.from()).unwrap();
+        let captured = captured_piece_type(board, mv);
+
+        &mut self.capture_history[piece.color() as usize][piece.ty() as usize][mv.to() as usize]
+            [captured as usize]
+    }
+
+    pub fn counter_move(&self, board: &Board) -> Move {
+        let Some(last_move) = board.last_move() else {
+            return Move::NULL;
+        };
+
+        let piece = board
+            .piece_at(last_move.to())
+            .expect("last moved piece should be on its target square");
+
+        self.counter_moves[piece.color() as usize][piece.ty() as usize][last_move.to() as usize]
+    }
+
+    /// Rewards the quiet move `best_move` that caused a beta cutoff and penalizes the quiet
+    /// moves that were searched before it without success
+    pub fn update_quiet_cutoff(
+        &mut self,
+        board: &Board,
+        continuations: &Continuations,
+        best_move: Move,
+        failed_quiets: &[Move],
+        depth: u8,
+    ) {
+        if let Some(last_move) = board.last_move() {
+            let piece = board
+                .piece_at(last_move.to())
+                .expect("last moved piece should be on its target square");
+
+            self.counter_moves[piece.color() as usize][piece.ty() as usize]
+                [last_move.to() as usize] = best_move;
+        }
+
+        let bonus = history_bonus(depth);
+        let side = board.side_to_move() as usize;
+
+        update_history(
+            &mut self.history[side][best_move.from() as usize][best_move.to() as usize],
+            bonus,
+        );
+
+        for mv in failed_quiets {
+            update_history(
+                &mut self.history[side][mv.from() as usize][mv.to() as usize],
+                -bonus,
+            );
+        }
+
+        for previous in continuations.iter().flatten() {
+            let index = continuation_index(*previous, piece_to(board, best_move));
+            update_history(&mut self.continuation_history[index], bonus);
+
+            for &mv in failed_quiets {
+                let index = continuation_index(*previous, piece_to(board, mv));
+                update_history(&mut self.continuation_history[index], -bonus);
+            }
+        }
+    }
+
+    /// Rewards `best_move` if it is a capture that caused a beta cutoff and penalizes the
+    /// captures that were searched before it without success
+    pub fn update_capture_cutoff(
+        &mut self,
+        board: &Board,
+        best_move: Move,
+        failed_captures: &[Move],
+        depth: u8,
+    ) {
+        let bonus = history_bonus(depth);
+
+        if best_move.is_capture() {
+            update_history(self.capture_history_entry_mut(board, best_move), bonus);
+        }
+
+        for &mv in failed_captures {
+            update_history(self.capture_history_entry_mut(board, mv), -bonus);
+        }
+    }
+}
+
+/// The moved piece and target square of `mv`, which has not been played on `board` yet
+fn piece_to(board: &Board, mv: Move) -> PieceTo {
+    PieceTo {
+        piece: board.piece_at(mv.from()).unwrap(),
+        to: mv.to(),
+    }
+}
+
+fn continuation_index(previous: PieceTo, current: PieceTo) -> usize {
+    previous.index() * NUM_COLORED_PIECES * NUM_SQUARES + current.index()
+}
+
+/// Type of the piece captured by `mv`, which is a pawn for en passant captures
+fn captured_piece_type(board: &Board, mv: Move) -> PieceType {
+    board.piece_at(mv.to()).map_or(PieceType::Pawn, |p| p.ty())
+}
+
+fn history_bonus(depth: u8) -> i32 {
+    (depth as i32 * depth as i32).min(1200)
+}
+
+/// Gravity update that keeps the entry within `[-MAX_HISTORY, MAX_HISTORY]`
+fn update_history(entry: &mut i16, bonus: i32) {
+    let value = *entry as i32;
+    *entry = (value + bonus - value * bonus.abs() / MAX_HISTORY) as i16;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    TTMove,
+    GenerateCaptures,
+    GoodCaptures,
+    FirstKiller,
+    SecondKiller,
+    CounterMove,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// Staged move picker that only generates and scores the moves of a stage once it is reached.
+///
+/// Moves are yielded in the following order:
+/// 1. transposition table move
+/// 2. captures that do not lose material according to SEE, ordered by MVV-LVA and capture
+///    history
+/// 3. killer moves
+/// 4. counter move
+/// 5. remaining quiet moves ordered by butterfly and continuation history
+/// 6. captures that lose material
+pub struct MovePicker {
+    stage: Stage,
+    /// Only yield the transposition table move and captures that do not lose material
+    captures_only: bool,
+    tt_move: Move,
+    killers: [Move; 2],
+    counter_move: Move,
+    continuations: Continuations,
+    moves: ArrayVec<(Move, i32), 256>,
+    bad_captures: MoveList,
+    index: usize,
+}
+
+impl MovePicker {
+    pub fn new(
+        board: &Board,
+        tables: &OrderingTables,
+        tt_move: Move,
+        killers: [Move; 2],
+        continuations: Continuations,
+    ) -> Self {
+        let counter_move = tables.counter_move(board);
+
+        Self {
+            stage: Stage::TTMove,
+            captures_only: false,
+            tt_move,
+            killers,
+            counter_move,
+            continuations,
+            moves: ArrayVec::new(),
+            bad_captures: MoveList::new(),
+            index: 0,
+        }
+    }
+
+    /// Move picker for the quiescence search that skips quiet moves and losing captures
+    pub fn new_quiescence(tt_move: Move) -> Self {
+        Self {
+            stage: Stage::TTMove,
+            captures_only: true,
+            tt_move: if tt_move.is_capture() {
+                tt_move
+            } else {
+                Move::NULL
+            },
+            killers: [Move::NULL; 2],
+            counter_move: Move::NULL,
+            continuations: [None; 2],
+            moves: ArrayVec::new(),
+            bad_captures: MoveList::new(),
+            index: 0,
+        }
+    }
+
+    pub fn next(&mut self, board: &Board, tables: &OrderingTables) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TTMove => {
+                    self.stage = Stage::GenerateCaptures;
+
+                    if self.tt_move != Move::NULL && is_legal(board, self.tt_move) {
+                        return Some(self.tt_move);
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    self.moves.clear();
+                    self.index = 0;
+
+                    for mv in board.generate_moves_captures_only() {
+                        let src_piece = board.piece_at(mv.from()).unwrap().ty();
+                        let dst_piece = captured_piece_type(board, mv);
+                        let score = mmv_lva(src_piece, dst_piece) * CAPTURE_MVV_LVA_SCALE
+                            + tables.capture_history(board, mv);
+                        self.moves.push((mv, score));
+                    }
+
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => {
+                    let Some(mv) = self.select_best() else {
+                        self.stage = if self.captures_only {
+                            Stage::Done
+                        } else {
+                            Stage::FirstKiller
+                        };
+                        continue;
+                    };
+
+                    if mv == self.tt_move {
+                        continue;
+                    }
+
+                    if !see(board, mv, 0) {
+                        self.bad_captures.push(mv);
+                        continue;
+                    }
+
+                    return Some(mv);
+                }
+                Stage::FirstKiller | Stage::SecondKiller => {
+                    let killer = self.killers[(self.stage == Stage::SecondKiller) as usize];
+
+                    self.stage = if self.stage == Stage::FirstKiller {
+                        Stage::SecondKiller
+                    } else {
+                        Stage::CounterMove
+                    };
+
+                    if killer != self.tt_move && killer.is_quiet() && is_legal(board, killer) {
+                        return Some(killer);
+                    }
+                }
+                Stage::CounterMove => {
+                    self.stage = Stage::GenerateQuiets;
+
+                    let counter_move = self.counter_move;
+                    if counter_move != self.tt_move
+                        && !self.killers.contains(&counter_move)
+                        && counter_move.is_quiet()
+                        && is_legal(board, counter_move)
+                    {
+                        return Some(counter_move);
+                    }
+                }
+                Stage::GenerateQuiets => {
+                    self.moves.clear();
+                    self.index = 0;
+
+                    for mv in board.generate_quiet_moves() {
+                        let score = if mv.is_quiet() {
+                            tables.history(board, mv)
+                                + tables.continuation_history(board, &self.continuations, mv)
+                        } else {
+                            QUIET_PROMOTION_SCORE
+                        };
+
+                        self.moves.push((mv, score));
+                    }
+
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    let Some(mv) = self.select_best() else {
+                        self.stage = Stage::BadCaptures;
+                        self.index = 0;
+                        continue;
+                    };
+
+                    if mv == self.tt_move || self.killers.contains(&mv) || mv == self.counter_move {
+                        continue;
+                    }
+
+                    return Some(mv);
+                }
+                Stage::BadCaptures => {
+                    let Some(&mv) = self.bad_captures.get(self.index) else {
+                        self.stage = Stage::Done;
+                        continue;
+                    };
+
+                    self.index += 1;
+                    return Some(mv);
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Selection sort step that swaps the best remaining move to the front
+    fn select_best(&mut self) -> Option<Move> {
+        if self.index >= self.moves.len() {
+            return None;
+        }
+
+        let remaining = &mut self.moves[self.index..];
+        let (best_index, _) = remaining
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, (_, score))| *score)
+            .unwrap();
+        remaining.swap(0, best_index);
+
+        let (mv, _) = self.moves[self.index];
+        self.index += 1;
+
+        Some(mv)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::moveord::mmv_lva;
-    use crate::types::piece::{ALL_PIECES, PieceType};
+    use std::str::FromStr;
+
+    use crate::board::Board;
+    use crate::moveord::{MAX_HISTORY, MovePicker, OrderingTables, PieceTo, mmv_lva};
+    use crate::types::chess_move::{Move, MoveFlag};
+    use crate::types::piece::{ALL_PIECES, Piece, PieceType};
+    use crate::types::square::Square;
 
     #[test]
     fn test() {
@@ -36,4 +471,164 @@ mod test {
             println!("{src_piece:?} takes {dst_piece:?}: {score}");
         }
     }
+
+    #[test]
+    fn test_quiet_cutoff_updates() {
+        let board = Board::default();
+        let mut tables = OrderingTables::default();
+
+        let best = Move::new(Square::G1, Square::F3, MoveFlag::Normal);
+        let failed = Move::new(Square::A2, Square::A3, MoveFlag::Normal);
+
+        tables.update_quiet_cutoff(&board, &[None; 2], best, &[failed], 5);
+
+        assert!(tables.history(&board, best) > 0);
+        assert!(tables.history(&board, failed) < 0);
+
+        let mut picker = MovePicker::new(&board, &tables, Move::NULL, [Move::NULL; 2], [None; 2]);
+        let mut moves = vec![];
+        while let Some(mv) = picker.next(&board, &tables) {
+            moves.push(mv);
+        }
+
+        assert_eq!(moves.len(), 20);
+        assert_eq!(moves[0], best);
+        assert_eq!(moves[19], failed);
+
+        tables.clear();
+        assert_eq!(tables.history(&board, best), 0);
+    }
+
+    #[test]
+    fn test_move_picker_stages() {
+        // white can capture a rook with the pawn (good) and a defended pawn with the queen (bad)
+        let board = Board::from_str("1k6/8/3p4/2r1p3/1P6/8/8/K3Q3 w - - 0 1").unwrap();
+        let tables = OrderingTables::default();
+        let tt_move = Move::new(Square::A1, Square::B2, MoveFlag::Normal);
+
+        let mut picker = MovePicker::new(&board, &tables, tt_move, [Move::NULL; 2], [None; 2]);
+        let mut moves = vec![];
+        while let Some(mv) = picker.next(&board, &tables) {
+            moves.push(mv);
+        }
+
+        let legal_moves = board.generate_moves();
+        assert_eq!(moves.len(), legal_moves.len());
+        assert!(legal_moves.iter().all(|m| moves.contains(m)));
+
+        assert_eq!(moves[0], tt_move);
+        assert_eq!(
+            moves[1],
+            Move::new(Square::B4, Square::C5, MoveFlag::Capture)
+        );
+        assert_eq!(
+            moves[moves.len() - 1],
+            Move::new(Square::E1, Square::E5, MoveFlag::Capture)
+        );
+    }
+
+    #[test]
+    fn test_losing_capture_after_quiets() {
+        // the queen can capture a pawn on e5 that is defended by the pawn on d6
+        let board = Board::from_str("1k6/8/3p4/2r1p3/8/8/8/K3Q3 w - - 0 1").unwrap();
+        let mut tables = OrderingTables::default();
+
+        let losing_capture = Move::new(Square::E1, Square::E5, MoveFlag::Capture);
+        let best_quiet = Move::new(Square::A1, Square::B1, MoveFlag::Normal);
+        let worst_quiet = Move::new(Square::A1, Square::A2, MoveFlag::Normal);
+        for _ in 0..100 {
+            tables.update_quiet_cutoff(&board, &[None; 2], best_quiet, &[worst_quiet], 40);
+        }
+        assert!(tables.history(&board, worst_quiet) < -MAX_HISTORY / 2);
+
+        let mut picker = MovePicker::new(&board, &tables, Move::NULL, [Move::NULL; 2], [None; 2]);
+        let mut moves = vec![];
+        while let Some(mv) = picker.next(&board, &tables) {
+            moves.push(mv);
+        }
+
+        // even the quiet move with the worst history is tried before losing material
+        assert_eq!(moves.last(), Some(&losing_capture));
+        assert_eq!(moves[moves.len() - 2], worst_quiet);
+        assert!(moves[..moves.len() - 1].iter().all(|mv| mv.is_quiet()));
+    }
+
+    #[test]
+    fn test_quiescence_picker_skips_quiets_and_losing_captures() {
+        let board = Board::from_str("1k6/8/3p4/2r1p3/1P6/8/8/K3Q3 w - - 0 1").unwrap();
+        let tables = OrderingTables::default();
+        let quiet_tt_move = Move::new(Square::A1, Square::B2, MoveFlag::Normal);
+
+        let mut picker = MovePicker::new_quiescence(quiet_tt_move);
+        let mut moves = vec![];
+        while let Some(mv) = picker.next(&board, &tables) {
+            moves.push(mv);
+        }
+
+        assert_eq!(
+            moves,
+            vec![Move::new(Square::B4, Square::C5, MoveFlag::Capture)]
+        );
+    }
+
+    #[test]
+    fn test_continuation_history() {
+        let board = Board::default();
+        let mut tables = OrderingTables::default();
+
+        let previous = PieceTo {
+            piece: Piece::BlackPawn,
+            to: Square::E5,
+        };
+        let best = Move::new(Square::G1, Square::F3, MoveFlag::Normal);
+        let failed = Move::new(Square::A2, Square::A3, MoveFlag::Normal);
+
+        tables.update_quiet_cutoff(&board, &[Some(previous), None], best, &[failed], 5);
+
+        assert!(tables.continuation_history(&board, &[Some(previous), None], best) > 0);
+        assert!(tables.continuation_history(&board, &[None, Some(previous)], failed) < 0);
+        assert_eq!(tables.continuation_history(&board, &[None; 2], best), 0);
+
+        tables.clear();
+        assert_eq!(
+            tables.continuation_history(&board, &[Some(previous), None], best),
+            0
+        );
+    }
+
+    #[test]
+    fn test_capture_history_refines_mvv_lva() {
+        // knight and bishop can both capture the undefended pawn on d5
+        let board = Board::from_str("4k3/8/8/3p4/8/2N5/6B1/4K3 w - - 0 1").unwrap();
+        let mut tables = OrderingTables::default();
+
+        let knight_capture = Move::new(Square::C3, Square::D5, MoveFlag::Capture);
+        let bishop_capture = Move::new(Square::G2, Square::D5, MoveFlag::Capture);
+
+        let mut picker = MovePicker::new(&board, &tables, Move::NULL, [Move::NULL; 2], [None; 2]);
+        assert_eq!(picker.next(&board, &tables), Some(knight_capture));
+
+        for _ in 0..100 {
+            tables.update_capture_cutoff(&board, bishop_capture, &[knight_capture], 40);
+        }
+        assert!(tables.capture_history(&board, bishop_capture) > 0);
+        assert!(tables.capture_history(&board, knight_capture) < 0);
+
+        let mut picker = MovePicker::new(&board, &tables, Move::NULL, [Move::NULL; 2], [None; 2]);
+        assert_eq!(picker.next(&board, &tables), Some(bishop_capture));
+        assert_eq!(picker.next(&board, &tables), Some(knight_capture));
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let board = Board::default();
+        let mut tables = OrderingTables::default();
+        let mv = Move::new(Square::E2, Square::E4, MoveFlag::DoublePawnPush);
+
+        for _ in 0..1000 {
+            tables.update_quiet_cutoff(&board, &[None; 2], mv, &[], 40);
+        }
+
+        assert!(tables.history(&board, mv) <= MAX_HISTORY);
+    }
 }
@@ -20,6 +20,11 @@ impl PrincipleVariation {
         self.inner[0]
     }
 
+    /// The expected reply to the best move
+    pub fn ponder_move(&self) -> Option<Move> {
+        self.inner.get(1).copied()
+    }
+
     pub fn line(&self) -> &[Move] {
         &self.inner
     }
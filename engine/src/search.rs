@@ -1,26 +1,104 @@
 use crate::board::Board;
-use crate::clock::Clock;
+use crate::clock::{Clock, node_share_factor, score_trend_factor, stability_factor};
+use crate::correction::CorrectionHistory;
 use crate::evaluation::Evaluation;
-use crate::evaluation::hce::board_value;
-use crate::moveord::mmv_lva;
-use crate::pv::PrincipleVariation;
+use crate::evaluation::hce::{board_value, raw_piece_value};
+use crate::movegen::{MoveList, is_legal};
+use crate::moveord::{Continuations, MovePicker, OrderingTables, PieceTo};
+use crate::pv::{MAX_PLY, PrincipleVariation};
+use crate::see::see;
+use crate::skill::Skill;
+use crate::stats::StatsBuffer;
 use crate::threadpool::StopSync;
 use crate::transposition::{Entry, TranspositionTable, ValueType};
+use crate::tree_dump::{CutoffReason, TreeDump};
 use crate::types::chess_move::Move;
 use crate::types::color::Color;
-use crate::types::search_limits::SearchLimits;
+use crate::types::piece::PieceType;
+use crate::types::search_limits::{SearchLimits, TimeLimit};
 use crate::uci::EngineMessage;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc::Sender;
-use web_time::Instant;
+use std::sync::{Arc, Condvar, Mutex};
+use web_time::{Duration, Instant};
+
+mod stack;
+
+pub use stack::{SearchStack, StackEntry};
+
+mod internal {
+    include!(concat!(env!("OUT_DIR"), "/reductions.rs"));
+}
+
+/// Minimum remaining depth for null move pruning
+const NMP_MIN_DEPTH: u8 = 3;
+/// Base depth reduction of the null move search
+const NMP_BASE_REDUCTION: u8 = 3;
+/// Minimum remaining depth at which a null move cutoff is verified by a reduced search
+const NMP_VERIFICATION_DEPTH: u8 = 12;
+
+/// Maximum remaining depth for reverse futility pruning
+const RFP_MAX_DEPTH: u8 = 8;
+/// Margin in centipawns per remaining ply by which the static evaluation has to exceed beta
+const RFP_MARGIN: i16 = 80;
+
+/// Maximum remaining depth for razoring
+const RAZOR_MAX_DEPTH: u8 = 3;
+/// Margin in centipawns per remaining ply by which the static evaluation has to stay below alpha
+const RAZOR_MARGIN: i16 = 250;
+
+/// Minimum depth for searching the root with an aspiration window
+const ASPIRATION_MIN_DEPTH: u8 = 4;
+/// Initial half width of the aspiration window in centipawns
+const ASPIRATION_DELTA: i16 = 25;
+
+/// Minimum remaining depth for singular extensions
+const SE_MIN_DEPTH: u8 = 8;
+/// Maximum number of plies the TT entry may be shallower than the node to test singularity
+const SE_TT_DEPTH_MARGIN: u8 = 3;
+/// Margin in centipawns per remaining ply below the TT score the other moves have to stay
+const SE_MARGIN: i16 = 2;
+
+/// Maximum remaining depth for late move pruning
+const LMP_MAX_DEPTH: u8 = 3;
+/// Number of moves searched at one ply of remaining depth before late quiet moves are pruned
+const LMP_BASE_MOVES: usize = 3;
+
+/// Maximum remaining depth for futility pruning of quiet moves
+const FP_MAX_DEPTH: u8 = 6;
+/// Base margin in centipawns by which a quiet move may raise the static evaluation
+const FP_BASE_MARGIN: i16 = 100;
+/// Additional margin in centipawns per remaining ply
+const FP_MARGIN: i16 = 100;
+
+/// Maximum remaining depth for SEE pruning
+const SEE_PRUNING_MAX_DEPTH: u8 = 6;
+/// Material in centipawns per remaining ply a quiet move may lose according to SEE
+const SEE_QUIET_MARGIN: i16 = 60;
+/// Material in centipawns per squared remaining ply a capture may lose according to SEE
+const SEE_CAPTURE_MARGIN: i16 = 20;
+
+/// Minimum remaining depth for late move reductions
+const LMR_MIN_DEPTH: u8 = 3;
+/// Number of moves searched at full depth before late move reductions kick in
+const LMR_FULL_DEPTH_MOVES: usize = 2;
+
+/// Time after which the main thread reports the root move it is currently searching
+const CURRMOVE_DELAY: Duration = Duration::from_secs(3);
+
+/// Safety margin in centipawns for delta pruning in the quiescence search
+const QS_DELTA_MARGIN: i16 = 200;
 
 pub struct ThreadData {
     pub engine_tx: Sender<EngineMessage>,
     pub tt: TranspositionTable,
     pub stop_sync: Arc<StopSync>,
     pub nodes_buffer: Arc<NodeCountBuffer>,
+    pub results: Arc<ThreadResults>,
+    pub stats: Arc<StatsBuffer>,
     pub thread_id: u8,
+    pub ordering: Box<OrderingTables>,
+    pub correction: Box<CorrectionHistory>,
 }
 
 pub struct Search {
@@ -29,12 +107,32 @@ pub struct Search {
     pub clock: Clock,
     pub root_moves: Vec<RootMove>,
     pub multipv: u8,
-
+    pub skill: Skill,
+    /// Penalty in centipawns for a draw from the perspective of the side to move at the root
+    pub contempt: i16,
+    /// Records the searched tree for debugging if set, ignored without the `tree_dump` feature
+    pub tree_dump: Option<Box<TreeDump>>,
+
+    stack: SearchStack,
+    /// Time the search was started by `go`. Unlike the start of the clock, this is kept after a
+    /// ponderhit, so the reported time and nps include the time spent pondering.
+    start: Instant,
+    root_color: Color,
     local_stop: bool,
     pv_index: usize,
     pv_last: usize,
     calls_until_stop_check: i16,
     completed_depth: u8,
+    /// Depth of the current iteration
+    root_depth: u8,
+    /// Highest ply reached in the current iteration, including the quiescence search
+    seldepth: u8,
+    is_main: bool,
+    /// Null move pruning is disabled for plies below this value during verification searches
+    nmp_min_ply: u8,
+    /// Whether pruning and reductions that can hide mates are enabled. They are turned off
+    /// while searching for a forced mate.
+    allow_pruning: bool,
 }
 
 impl Search {
@@ -44,19 +142,44 @@ impl Search {
         clock: Clock,
         root_moves: Vec<RootMove>,
         multipv: u8,
+        skill: Skill,
+        contempt: i16,
     ) -> Self {
+        let mut limits = limits;
+        if let Some(depth) = skill.depth_limit() {
+            limits.depth = Some(limits.depth.map_or(depth, |d| d.min(depth)));
+        }
+        if let Some(nodes) = skill.nodes_limit() {
+            limits.nodes = Some(limits.nodes.map_or(nodes, |n| n.min(nodes)));
+        }
+
+        let allow_pruning = limits.mate.is_none();
+        let root_color = board.side_to_move();
+        let start = clock.start;
+
         Self {
             board,
             limits,
             clock,
             root_moves,
             multipv,
+            skill,
+            contempt,
+            tree_dump: None,
 
+            stack: SearchStack::default(),
+            start,
+            root_color,
             local_stop: false,
             pv_index: 0,
             pv_last: 0,
             calls_until_stop_check: 0,
             completed_depth: 0,
+            root_depth: 0,
+            seldepth: 0,
+            is_main: false,
+            nmp_min_ply: 0,
+            allow_pruning,
         }
     }
 
@@ -79,10 +202,10 @@ impl Search {
                 .unwrap();
         }
 
-        let _guard = td.stop_sync.cond_var.wait_while(
+        drop(td.stop_sync.cond_var.wait_while(
             td.stop_sync.wait_for_stop.lock().unwrap(),
             |wait_for_stop| *wait_for_stop,
-        );
+        ));
 
         if self.root_moves.is_empty() {
             if is_main {
@@ -93,42 +216,99 @@ impl Search {
             return Move::NULL;
         }
 
-        let best_move = self.root_moves[0].pv.best_move();
+        let root_move = &self.root_moves[0];
+        td.results.publish(
+            td.thread_id,
+            ThreadResult {
+                score: root_move.score,
+                completed_depth: self.completed_depth,
+                pv: root_move.pv.clone(),
+            },
+        );
 
-        if is_main {
-            td.engine_tx
-                .send(EngineMessage::Response(format!("bestmove {best_move}")))
-                .unwrap();
+        if !is_main {
+            return root_move.pv.best_move();
         }
 
+        let mut pv = td.results.vote();
+        if self.skill.enabled() {
+            let weakened_move = self
+                .skill
+                .pick_move(&self.root_moves, self.multipv as usize);
+            pv = self
+                .root_moves
+                .iter()
+                .find(|root_move| root_move.pv.best_move() == weakened_move)
+                .map_or(pv, |root_move| root_move.pv.clone());
+        }
+        let best_move = pv.best_move();
+
+        if StatsBuffer::enabled() {
+            let output = format!("info string {}", td.stats.accumulate().summary());
+            td.engine_tx.send(EngineMessage::Response(output)).unwrap();
+        }
+
+        let output = match self.ponder_move(td, &pv) {
+            Some(ponder_move) => format!("bestmove {best_move} ponder {ponder_move}"),
+            None => format!("bestmove {best_move}"),
+        };
+
+        td.engine_tx.send(EngineMessage::Response(output)).unwrap();
+
         best_move
     }
 
+    /// The expected reply to the best move, taken from the principal variation or the
+    /// transposition table if the principal variation ends after the best move
+    fn ponder_move(&mut self, td: &ThreadData, pv: &PrincipleVariation) -> Option<Move> {
+        if let Some(ponder_move) = pv.ponder_move() {
+            return Some(ponder_move);
+        }
+
+        self.board.apply_move(pv.best_move());
+        let ponder_move = td
+            .tt
+            .probe(&self.board, 1)
+            .map(|entry| entry.best_move)
+            .filter(|&m| m != Move::NULL && is_legal(&self.board, m));
+        self.board.undo_move();
+
+        ponder_move
+    }
+
     pub fn iterative_deepening(&mut self, td: &mut ThreadData, is_main: bool) {
+        let mut best_move_stability = 0u8;
+        let mut previous_best: Option<(Move, Evaluation)> = None;
+        self.is_main = is_main;
+
         for depth in 1..u8::MAX {
+            if !is_main
+                && depth > 1
+                && depth < self.max_depth()
+                && skip_depth(td.thread_id, depth, self.board.game_ply())
+            {
+                continue;
+            }
+
+            self.seldepth = 0;
+            self.root_depth = depth;
+
+            for root_move in &mut self.root_moves {
+                root_move.previous_score = root_move.score;
+            }
+
             for pv_index in 0..self.multipv {
                 self.pv_index = pv_index as usize;
                 self.pv_last = self.root_moves.len() - 1;
 
-                let mut pv = PrincipleVariation::default();
-
-                self.negamax_search::<true, true>(
-                    td,
-                    Evaluation::MIN,
-                    Evaluation::MAX,
-                    depth,
-                    0,
-                    &mut pv,
-                );
-
-                self.root_moves[(self.pv_index)..=(self.pv_last)].sort();
+                self.aspiration_search(td, depth, is_main);
 
                 if self.local_stop {
                     break;
                 }
 
                 if is_main {
-                    let output = self.info_string(td, depth).unwrap();
+                    let output = self.info_string(td, depth, None).unwrap();
 
                     td.engine_tx.send(EngineMessage::Response(output)).unwrap();
                 }
@@ -138,119 +318,493 @@ impl Search {
                 break;
             }
 
-            if depth >= self.limits.depth.unwrap_or(u8::MAX) {
+            // helper threads skip depths, so count the depth instead of the iterations
+            self.completed_depth = depth;
+
+            if self.found_requested_mate() {
                 break;
             }
 
-            if let Some(optimum) = self.clock.optimum
-                && optimum < Instant::now()
-            {
+            if depth >= self.max_depth() {
+                if is_main && self.limits.mate.is_some() {
+                    td.engine_tx
+                        .send(EngineMessage::Response(
+                            "info string no mate found".to_owned(),
+                        ))
+                        .unwrap();
+                }
+                break;
+            }
+
+            let best = &self.root_moves[0];
+            let mut score_drop = 0;
+            if let Some((previous_move, previous_score)) = previous_best {
+                if previous_move == best.pv.best_move() {
+                    best_move_stability = best_move_stability.saturating_add(1);
+                } else {
+                    best_move_stability = 0;
+                }
+
+                if !previous_score.is_mate() && !best.score.is_mate() {
+                    score_drop = previous_score.val() - best.score.val();
+                }
+            }
+            previous_best = Some((best.pv.best_move(), best.score));
+
+            // only the main thread decides when to stop, the other threads follow
+            if is_main && self.clock.optimum.is_some() {
+                let nodes = td.nodes_buffer.get(td.thread_id).load(Ordering::Relaxed);
+                let best_move_nodes = best.nodes as f64 / nodes.max(1) as f64;
+
+                // a fixed move time is a hard budget and not scaled
+                let scale = if matches!(self.limits.time, TimeLimit::Dynamic { .. }) {
+                    stability_factor(best_move_stability)
+                        * score_trend_factor(score_drop)
+                        * node_share_factor(best_move_nodes)
+                } else {
+                    1.0
+                };
+
+                // there is nothing to think about with a single legal move
+                if self.root_moves.len() == 1
+                    || self.clock.scaled_optimum(scale).is_some_and(|optimum| {
+                        optimum < self.clock.now(td.nodes_buffer.accumulate())
+                    })
+                {
+                    break;
+                }
+            }
+        }
+
+        if is_main {
+            // let the other threads know that the search is over
+            td.stop_sync.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Maximum depth of the iterative deepening loop.
+    ///
+    /// A mate in `n` moves is at most `2n - 1` plies deep, so a mate search does not need to
+    /// look any further.
+    fn max_depth(&self) -> u8 {
+        let depth = self.limits.depth.unwrap_or(u8::MAX);
+
+        match self.limits.mate {
+            Some(mate) => depth.min(mate.saturating_mul(2).saturating_sub(1).max(1)),
+            None => depth,
+        }
+    }
+
+    /// Whether the best root move is proven to mate within the number of moves requested
+    /// by `go mate`
+    fn found_requested_mate(&self) -> bool {
+        let Some(mate) = self.limits.mate else {
+            return false;
+        };
+
+        let score = self.root_moves[0].score;
+        score.is_mate() && score.mate_num_ply() > 0 && score.mate_full_moves() as u8 <= mate
+    }
+
+    /// Searches the root with a window centred on the score of the previous iteration.
+    ///
+    /// The window is widened on the failing side until the score lies within its bounds.
+    fn aspiration_search(&mut self, td: &mut ThreadData, depth: u8, is_main: bool) {
+        let previous_score = self.root_moves[self.pv_index].previous_score;
+
+        let mut delta = ASPIRATION_DELTA;
+        let (mut alpha, mut beta) = if depth >= ASPIRATION_MIN_DEPTH
+            && previous_score != Evaluation::MIN
+            && !previous_score.is_mate()
+        {
+            (
+                previous_score.saturating_add(-delta),
+                previous_score.saturating_add(delta),
+            )
+        } else {
+            (Evaluation::MIN, Evaluation::MAX)
+        };
+
+        loop {
+            let score = self.negamax_search::<true, true>(td, alpha, beta, depth, 0);
+
+            self.root_moves[(self.pv_index)..=(self.pv_last)].sort();
+
+            if self.local_stop {
+                break;
+            }
+
+            let bound = if score <= alpha {
+                alpha = score.saturating_add(-delta);
+                ValueType::Upperbound
+            } else if score >= beta {
+                beta = score.saturating_add(delta);
+                ValueType::Lowerbound
+            } else {
                 break;
+            };
+
+            if is_main {
+                let output = self.info_string(td, depth, Some(bound)).unwrap();
+
+                td.engine_tx.send(EngineMessage::Response(output)).unwrap();
             }
 
-            self.completed_depth += 1;
+            delta = delta.saturating_add(delta / 2);
         }
     }
 
     /// Fail soft variant of negamax search
+    #[inline(always)]
     fn negamax_search<const PV: bool, const ROOT: bool>(
+        &mut self,
+        td: &mut ThreadData,
+        alpha: Evaluation,
+        beta: Evaluation,
+        depth: u8,
+        ply: u8,
+    ) -> Evaluation {
+        if TreeDump::enabled()
+            && let Some(tree_dump) = &mut self.tree_dump
+        {
+            tree_dump.enter(self.board.last_move(), ply, depth, alpha, beta, false);
+            let score = self.negamax_node::<PV, ROOT>(td, alpha, beta, depth, ply);
+            self.dump(|tree_dump| tree_dump.exit(score));
+            return score;
+        }
+
+        self.negamax_node::<PV, ROOT>(td, alpha, beta, depth, ply)
+    }
+
+    fn negamax_node<const PV: bool, const ROOT: bool>(
         &mut self,
         td: &mut ThreadData,
         mut alpha: Evaluation,
         mut beta: Evaluation,
         mut depth: u8,
         ply: u8,
-        pv: &mut PrincipleVariation,
     ) -> Evaluation {
-        let mut child_pv = PrincipleVariation::default();
-
         if PV {
-            pv.clear();
+            self.stack[ply].pv.clear();
         }
 
         if self.should_stop(td) {
+            self.dump(|tree_dump| tree_dump.cutoff(CutoffReason::Stop));
             return Evaluation::INVALID;
         }
 
         if !ROOT {
             if self.board.is_repetition() || self.board.is_draw_by_fifty_move_rule() {
-                return Evaluation::EQUALITY;
+                self.dump(|tree_dump| tree_dump.cutoff(CutoffReason::Draw));
+                return self.draw_score();
             }
 
             alpha = alpha.max(Evaluation::mated_in(ply));
             beta = beta.min(Evaluation::mate_in(ply + 1));
 
             if alpha >= beta {
+                self.dump(|tree_dump| tree_dump.cutoff(CutoffReason::MateDistance));
                 return alpha;
             }
 
+            if ply as usize >= MAX_PLY - 1 {
+                return self.evaluate();
+            }
+
             if !self.board.checkers().is_empty() {
                 depth += 1;
             }
         }
 
         if depth == 0 {
-            return self.quiescence(td, alpha, beta, ply);
+            return self.quiescence::<PV>(td, alpha, beta, ply, true);
         }
 
         td.nodes_buffer
             .get(td.thread_id)
             .fetch_add(1, Ordering::Relaxed);
+        td.stats.node(td.thread_id, false);
+        self.seldepth = self.seldepth.max(ply);
+
+        // Searches with an excluded move verify the singularity of the TT move. Their result is
+        // not valid for the position, so they can neither use nor store TT scores.
+        let excluded_move = self.stack[ply].excluded_move;
+        let is_verification = excluded_move != Move::NULL;
 
         let entry = td.tt.probe(&self.board, ply);
+        td.stats.tt_probe(td.thread_id, entry.is_some());
+        if entry.is_some() {
+            self.dump(TreeDump::tt_hit);
+        }
         if let Some(entry) = &entry
             && !PV
+            && !is_verification
             && entry.depth >= depth
             && tt_cutoff(entry, alpha, beta)
         {
+            td.stats.tt_cutoff(td.thread_id);
+            self.dump(|tree_dump| tree_dump.cutoff(CutoffReason::TranspositionTable));
             return entry.value;
         }
 
-        let mut moves = self.board.generate_moves();
-        if moves.is_empty() {
-            if !self.board.checkers().is_empty() {
-                return Evaluation::mated_in(ply);
-            } else {
-                return Evaluation::EQUALITY;
+        let in_check = !self.board.checkers().is_empty();
+        let raw_eval = (!in_check).then(|| self.evaluate());
+        let static_eval = raw_eval.map(|eval| td.correction.correct(&self.board, eval));
+        self.stack[ply].in_check = in_check;
+        self.stack[ply].static_eval = static_eval;
+
+        if !PV
+            && !is_verification
+            && self.allow_pruning
+            && let Some(static_eval) = static_eval
+        {
+            // Reverse futility pruning: a position that is far above beta at shallow depth
+            // will most likely stay above beta
+            if depth <= RFP_MAX_DEPTH
+                && !beta.is_mate()
+                && static_eval - RFP_MARGIN * (depth as i16) >= beta
+            {
+                self.dump(|tree_dump| tree_dump.cutoff(CutoffReason::ReverseFutility));
+                return static_eval;
+            }
+
+            // Razoring: a position that is far below alpha at shallow depth is only worth
+            // searching for tactics
+            if depth <= RAZOR_MAX_DEPTH && static_eval + RAZOR_MARGIN * (depth as i16) < alpha {
+                let score = self.quiescence::<false>(td, alpha, beta, ply, true);
+                if score <= alpha {
+                    self.dump(|tree_dump| tree_dump.cutoff(CutoffReason::Razoring));
+                    return score;
+                }
             }
         }
 
-        let original_alpha = alpha;
-        let mut best_score = Evaluation::MIN;
-        let mut best_move = Move::NULL;
+        // Null move pruning: if passing the turn still fails high, the position is most likely
+        // good enough to cut off. Positions with only pawns left are skipped due to zugzwang.
+        if !PV
+            && !in_check
+            && !is_verification
+            && self.allow_pruning
+            && depth >= NMP_MIN_DEPTH
+            && ply >= self.nmp_min_ply
+            && self.board.last_move().is_some()
+            && !beta.is_mate()
+            && self.board.has_non_pawn_material(self.board.side_to_move())
+            && let Some(static_eval) = static_eval
+            && static_eval >= beta
+        {
+            let eval_reduction = ((static_eval.val() - beta.val()) / 200).min(3) as u8;
+            let reduction = NMP_BASE_REDUCTION + depth / 3 + eval_reduction;
+            let null_depth = depth.saturating_sub(reduction);
 
-        moves.sort_by_key(|mov| {
-            if let Some(entry) = &entry
-                && &entry.best_move == mov
-            {
-                return -200000;
+            self.stack[ply].current_move = Move::NULL;
+            self.stack[ply].moved_piece = None;
+
+            self.board.make_null();
+            let null_score =
+                -self.negamax_search::<false, false>(td, -beta, -beta + 1, null_depth, ply + 1);
+            self.board.undo_null();
+
+            if self.local_stop {
+                return Evaluation::INVALID;
             }
 
-            let src_piece = self.board.piece_at(mov.from()).unwrap();
-            let dst_piece = self.board.piece_at(mov.to());
-            if let Some(dst_piece) = dst_piece {
-                return -mmv_lva(src_piece.ty(), dst_piece.ty());
+            if null_score >= beta {
+                // don't trust unproven mate scores
+                let null_score = if null_score.is_mate() {
+                    beta
+                } else {
+                    null_score
+                };
+
+                if self.nmp_min_ply != 0 || depth < NMP_VERIFICATION_DEPTH {
+                    self.dump(|tree_dump| tree_dump.cutoff(CutoffReason::NullMove));
+                    return null_score;
+                }
+
+                // Verify the cutoff at high depths with null move pruning disabled for the
+                // first plies to guard against zugzwang
+                self.nmp_min_ply = ply + 3 * null_depth / 4;
+                let verification_score =
+                    self.negamax_search::<false, false>(td, beta - 1, beta, null_depth, ply);
+                self.nmp_min_ply = 0;
+
+                if self.local_stop {
+                    return Evaluation::INVALID;
+                }
+
+                if verification_score >= beta {
+                    self.dump(|tree_dump| tree_dump.cutoff(CutoffReason::NullMove));
+                    return null_score;
+                }
             }
-            0
-        });
+        }
 
+        let original_alpha = alpha;
+        let mut best_score = Evaluation::MIN;
+        let mut best_move = Move::NULL;
+
+        let tt_move = entry.as_ref().map_or(Move::NULL, |entry| entry.best_move);
+        let continuations = self.continuations(ply);
+        let mut move_picker = MovePicker::new(
+            &self.board,
+            &td.ordering,
+            tt_move,
+            self.stack[ply].killers,
+            continuations,
+        );
+
+        let mut quiets_searched = MoveList::new();
+        let mut captures_searched = MoveList::new();
         let mut move_count = 0;
-        for chess_move in moves {
+        while let Some(chess_move) = move_picker.next(&self.board, &td.ordering) {
             if ROOT && !self.root_moves[self.pv_index..=self.pv_last].includes_root(chess_move) {
                 continue;
             }
 
+            if chess_move == excluded_move {
+                continue;
+            }
+
             move_count += 1;
 
+            // Move pruning: skip moves that are unlikely to raise alpha once a move has been
+            // found that does not get mated
+            if !ROOT
+                && self.allow_pruning
+                && let Some(static_eval) = static_eval
+                && best_score != Evaluation::MIN
+                && !best_score.is_mate()
+            {
+                if chess_move.is_quiet() {
+                    // Late move pruning
+                    if depth <= LMP_MAX_DEPTH
+                        && move_count > LMP_BASE_MOVES + (depth as usize * depth as usize)
+                    {
+                        continue;
+                    }
+
+                    // Futility pruning
+                    if depth <= FP_MAX_DEPTH
+                        && static_eval + FP_BASE_MARGIN + FP_MARGIN * (depth as i16) <= alpha
+                    {
+                        continue;
+                    }
+                }
+
+                // SEE pruning
+                let see_threshold = if chess_move.is_quiet() {
+                    -SEE_QUIET_MARGIN * depth as i16
+                } else {
+                    -SEE_CAPTURE_MARGIN * (depth as i16) * (depth as i16)
+                };
+                if depth <= SEE_PRUNING_MAX_DEPTH && !see(&self.board, chess_move, see_threshold) {
+                    continue;
+                }
+            }
+
+            if ROOT && self.is_main && self.start.elapsed() > CURRMOVE_DELAY {
+                td.engine_tx
+                    .send(EngineMessage::Response(format!(
+                        "info depth {depth} currmove {chess_move} currmovenumber {}",
+                        move_count + self.pv_index
+                    )))
+                    .unwrap();
+            }
+
+            // Singular extensions: if the TT move is much better than all other moves according
+            // to a reduced search, it is extended. If even the other moves fail high, the node
+            // most likely fails high as well (multi-cut). Lines that were already extended a lot
+            // are not extended any further, so repeated extensions can not blow up the search.
+            let mut extension = 0;
+            if !ROOT
+                && !is_verification
+                && ply < self.root_depth.saturating_mul(2)
+                && chess_move == tt_move
+                && depth >= SE_MIN_DEPTH
+                && let Some(entry) = &entry
+                && entry.value_type != ValueType::Upperbound
+                && entry.depth + SE_TT_DEPTH_MARGIN >= depth
+                && !entry.value.is_mate()
+            {
+                let singular_beta = entry.value - SE_MARGIN * (depth as i16);
+                let singular_depth = (depth - 1) / 2;
+
+                self.stack[ply].excluded_move = chess_move;
+                let score = self.negamax_search::<false, false>(
+                    td,
+                    singular_beta - 1,
+                    singular_beta,
+                    singular_depth,
+                    ply,
+                );
+                self.stack[ply].excluded_move = Move::NULL;
+
+                if self.local_stop {
+                    return Evaluation::INVALID;
+                }
+
+                if score < singular_beta {
+                    extension = 1;
+                } else if self.allow_pruning && singular_beta >= beta {
+                    self.dump(|tree_dump| tree_dump.cutoff(CutoffReason::MultiCut));
+                    return singular_beta;
+                }
+            }
+            let new_depth = depth - 1 + extension;
+
+            let nodes_before = td.nodes_buffer.get(td.thread_id).load(Ordering::Relaxed);
+
+            self.stack[ply].current_move = chess_move;
+            self.stack[ply].moved_piece = self.board.piece_at(chess_move.from());
+
             self.board.apply_move(chess_move);
-            let score = -self.negamax_search::<PV, false>(
-                td,
-                -beta,
-                -alpha,
-                depth - 1,
-                ply + 1,
-                &mut child_pv,
-            );
+            let score = if move_count == 1 {
+                -self.negamax_search::<PV, false>(td, -beta, -alpha, new_depth, ply + 1)
+            } else {
+                // Late move reductions: quiet moves late in the move ordering rarely turn out
+                // to be best, so they are searched with reduced depth first
+                let gives_check = !self.board.checkers().is_empty();
+                let reduction = if self.allow_pruning
+                    && depth >= LMR_MIN_DEPTH
+                    && move_count > LMR_FULL_DEPTH_MOVES + PV as usize
+                    && chess_move.is_quiet()
+                    && !in_check
+                    && !gives_check
+                {
+                    lmr_reduction::<PV>(depth, move_count)
+                } else {
+                    0
+                };
+
+                // Principal variation search: later moves are expected to be worse than the
+                // first, so prove that with a null window and only re-search if that fails
+                self.stack[ply + 1].pv.clear();
+                let mut score = -self.negamax_search::<false, false>(
+                    td,
+                    -alpha - 1,
+                    -alpha,
+                    new_depth - reduction,
+                    ply + 1,
+                );
+
+                if reduction > 0 && score > alpha {
+                    score = -self.negamax_search::<false, false>(
+                        td,
+                        -alpha - 1,
+                        -alpha,
+                        new_depth,
+                        ply + 1,
+                    );
+                }
+
+                if PV && score > alpha && (ROOT || score < beta) {
+                    score =
+                        -self.negamax_search::<true, false>(td, -beta, -alpha, new_depth, ply + 1);
+                }
+
+                score
+            };
             self.board.undo_move();
 
             if self.local_stop {
@@ -259,10 +813,12 @@ impl Search {
 
             if ROOT {
                 let root_move = self.root_moves.find_root_mut(chess_move).unwrap();
+                root_move.nodes +=
+                    td.nodes_buffer.get(td.thread_id).load(Ordering::Relaxed) - nodes_before;
 
                 if move_count == 1 || score > alpha {
                     root_move.score = score;
-                    root_move.pv.load_from(chess_move, &child_pv);
+                    root_move.pv.load_from(chess_move, &self.stack[ply + 1].pv);
                 } else {
                     root_move.score = Evaluation::MIN;
                     root_move.pv.truncate_to_root();
@@ -277,106 +833,383 @@ impl Search {
                     alpha = score;
 
                     if !ROOT && PV {
-                        pv.load_from(chess_move, &child_pv);
+                        self.stack.update_pv(ply, chess_move);
                     }
                 }
             }
 
             if alpha >= beta {
+                td.stats.beta_cutoff(td.thread_id, move_count);
+                self.dump(|tree_dump| {
+                    tree_dump.cutoff(CutoffReason::Beta {
+                        mv: chess_move,
+                        move_number: move_count,
+                    })
+                });
+
+                if chess_move.is_quiet() {
+                    self.stack.update_killers(ply, chess_move);
+                    td.ordering.update_quiet_cutoff(
+                        &self.board,
+                        &continuations,
+                        chess_move,
+                        &quiets_searched,
+                        depth,
+                    );
+                }
+                td.ordering.update_capture_cutoff(
+                    &self.board,
+                    chess_move,
+                    &captures_searched,
+                    depth,
+                );
                 break;
             }
+
+            if chess_move.is_quiet() {
+                quiets_searched.push(chess_move);
+            } else if chess_move.is_capture() {
+                captures_searched.push(chess_move);
+            }
         }
 
-        let value_type = get_value_type(best_score, original_alpha, beta);
+        if move_count == 0 {
+            return if is_verification {
+                // the excluded move is the only legal move
+                alpha
+            } else if in_check {
+                Evaluation::mated_in(ply)
+            } else {
+                self.draw_score()
+            };
+        }
 
-        td.tt
-            .store(&self.board, best_move, depth, best_score, value_type, ply);
+        if !is_verification {
+            let value_type = get_value_type(best_score, original_alpha, beta);
+
+            // A bound only tells that the static eval was wrong if it lies on the far side of it.
+            // Captures and promotions are expected to change the material balance.
+            if let Some(raw_eval) = raw_eval
+                && let Some(static_eval) = static_eval
+                && (best_move == Move::NULL || best_move.is_quiet())
+                && !best_score.is_mate()
+                && !(value_type == ValueType::Lowerbound && best_score <= static_eval)
+                && !(value_type == ValueType::Upperbound && best_score >= static_eval)
+            {
+                td.correction
+                    .update(&self.board, depth, raw_eval, best_score);
+            }
+
+            td.tt
+                .store(&self.board, best_move, depth, best_score, value_type, ply);
+        }
 
         best_score
     }
 
-    fn quiescence(
+    /// Quiescence search that only considers captures (and quiet checks on its first ply)
+    /// to resolve tactical sequences at the horizon. All evasions are searched when in check.
+    #[inline(always)]
+    fn quiescence<const PV: bool>(
+        &mut self,
+        td: &mut ThreadData,
+        alpha: Evaluation,
+        beta: Evaluation,
+        ply: u8,
+        generate_checks: bool,
+    ) -> Evaluation {
+        if TreeDump::enabled()
+            && let Some(tree_dump) = &mut self.tree_dump
+        {
+            tree_dump.enter(self.board.last_move(), ply, 0, alpha, beta, true);
+            let score = self.quiescence_node::<PV>(td, alpha, beta, ply, generate_checks);
+            self.dump(|tree_dump| tree_dump.exit(score));
+            return score;
+        }
+
+        self.quiescence_node::<PV>(td, alpha, beta, ply, generate_checks)
+    }
+
+    fn quiescence_node<const PV: bool>(
         &mut self,
         td: &mut ThreadData,
         mut alpha: Evaluation,
         beta: Evaluation,
         ply: u8,
+        generate_checks: bool,
     ) -> Evaluation {
+        if PV {
+            self.stack[ply].pv.clear();
+        }
+
+        if self.should_stop(td) {
+            self.dump(|tree_dump| tree_dump.cutoff(CutoffReason::Stop));
+            return Evaluation::INVALID;
+        }
+
         td.nodes_buffer
             .get(td.thread_id)
             .fetch_add(1, Ordering::Relaxed);
+        td.stats.node(td.thread_id, true);
+        self.seldepth = self.seldepth.max(ply);
 
-        let mut moves = self.board.generate_moves();
-        if moves.is_empty() {
-            if !self.board.checkers().is_empty() {
-                return Evaluation::mated_in(ply);
-            } else {
-                return Evaluation::EQUALITY;
-            }
+        if ply as usize >= MAX_PLY - 1 {
+            return self.evaluate();
         }
 
-        let evaluation = match self.board.side_to_move() {
-            Color::White => board_value(&self.board),
-            Color::Black => -board_value(&self.board),
-        };
+        let entry = td.tt.probe(&self.board, ply);
+        td.stats.tt_probe(td.thread_id, entry.is_some());
+        if entry.is_some() {
+            self.dump(TreeDump::tt_hit);
+        }
+        if let Some(entry) = &entry
+            && !PV
+            && tt_cutoff(entry, alpha, beta)
+        {
+            td.stats.tt_cutoff(td.thread_id);
+            self.dump(|tree_dump| tree_dump.cutoff(CutoffReason::TranspositionTable));
+            return entry.value;
+        }
 
-        alpha = alpha.max(evaluation);
+        let in_check = !self.board.checkers().is_empty();
+        let original_alpha = alpha;
 
-        if alpha >= beta {
-            return evaluation;
+        // Standing pat would score a stalemate by its material. Detecting it needs a full move
+        // generation, which is only cheap enough with just pawns and the king left.
+        if generate_checks
+            && !in_check
+            && !self.board.has_non_pawn_material(self.board.side_to_move())
+            && self.board.generate_moves().is_empty()
+        {
+            return self.draw_score();
         }
 
-        moves.retain(|m| m.is_capture());
+        // there is no stand pat option when in check since every evasion has to be considered
+        let stand_pat = if in_check {
+            Evaluation::MIN
+        } else {
+            let evaluation = td.correction.correct(&self.board, self.evaluate());
 
-        moves.sort_by_key(|mov| {
-            let src_piece = self.board.piece_at(mov.from()).unwrap();
-            let dst_piece = self.board.piece_at(mov.to());
-            if let Some(dst_piece) = dst_piece {
-                return -mmv_lva(src_piece.ty(), dst_piece.ty());
+            if evaluation >= beta {
+                self.dump(|tree_dump| tree_dump.cutoff(CutoffReason::StandPat));
+                return evaluation;
             }
-            0
-        });
 
-        let mut best_score = evaluation;
-        for chess_move in moves {
-            self.board.apply_move(chess_move);
-            let score = -self.quiescence(td, -beta, -alpha, ply + 1);
-            self.board.undo_move();
+            alpha = alpha.max(evaluation);
+            evaluation
+        };
+
+        let tt_move = entry.as_ref().map_or(Move::NULL, |entry| entry.best_move);
+        let mut move_picker = if in_check {
+            MovePicker::new(
+                &self.board,
+                &td.ordering,
+                tt_move,
+                self.stack[ply].killers,
+                self.continuations(ply),
+            )
+        } else {
+            MovePicker::new_quiescence(tt_move)
+        };
+
+        let mut best_score = stand_pat;
+        let mut best_move = Move::NULL;
+        let mut move_count = 0;
+
+        while let Some(chess_move) = move_picker.next(&self.board, &td.ordering) {
+            move_count += 1;
+
+            // Delta pruning: skip captures that can not raise the score to alpha even if the
+            // captured piece was won for free
+            if !in_check && chess_move.promotion().is_none() {
+                let captured = self
+                    .board
+                    .piece_at(chess_move.to())
+                    .map_or(PieceType::Pawn, |p| p.ty());
+
+                if stand_pat.val() + raw_piece_value(captured) + QS_DELTA_MARGIN <= alpha.val() {
+                    continue;
+                }
+            }
+
+            let score = self.quiescence_child::<PV>(td, chess_move, alpha, beta, ply);
+
+            if self.local_stop {
+                return Evaluation::INVALID;
+            }
 
             if score > best_score {
                 best_score = score;
+                best_move = chess_move;
                 if score > alpha {
                     alpha = score;
+
+                    // keeps mating lines complete where the main search hands over
+                    if PV {
+                        self.stack.update_pv(ply, chess_move);
+                    }
                 }
             }
 
             if alpha >= beta {
+                self.dump(|tree_dump| {
+                    tree_dump.cutoff(CutoffReason::Beta {
+                        mv: chess_move,
+                        move_number: move_count,
+                    })
+                });
                 break;
             }
         }
+
+        if in_check && move_count == 0 {
+            return Evaluation::mated_in(ply);
+        }
+
+        if generate_checks && !in_check && alpha < beta {
+            for chess_move in self.board.generate_quiet_moves() {
+                if !chess_move.is_quiet()
+                    || !self.board.gives_check(chess_move)
+                    || !see(&self.board, chess_move, 0)
+                {
+                    continue;
+                }
+
+                move_count += 1;
+
+                let score = self.quiescence_child::<PV>(td, chess_move, alpha, beta, ply);
+
+                if self.local_stop {
+                    return Evaluation::INVALID;
+                }
+
+                if score > best_score {
+                    best_score = score;
+                    best_move = chess_move;
+                    if score > alpha {
+                        alpha = score;
+
+                        if PV {
+                            self.stack.update_pv(ply, chess_move);
+                        }
+                    }
+                }
+
+                if alpha >= beta {
+                    self.dump(|tree_dump| {
+                        tree_dump.cutoff(CutoffReason::Beta {
+                            mv: chess_move,
+                            move_number: move_count,
+                        })
+                    });
+                    break;
+                }
+            }
+        }
+
+        let value_type = get_value_type(best_score, original_alpha, beta);
+        td.tt
+            .store(&self.board, best_move, 0, best_score, value_type, ply);
+
         best_score
     }
 
+    fn quiescence_child<const PV: bool>(
+        &mut self,
+        td: &mut ThreadData,
+        chess_move: Move,
+        alpha: Evaluation,
+        beta: Evaluation,
+        ply: u8,
+    ) -> Evaluation {
+        self.stack[ply].current_move = chess_move;
+        self.stack[ply].moved_piece = self.board.piece_at(chess_move.from());
+
+        self.board.apply_move(chess_move);
+        let score = -self.quiescence::<PV>(td, -beta, -alpha, ply + 1, false);
+        self.board.undo_move();
+        score
+    }
+
+    /// Score of a drawn position from the perspective of the side to move.
+    ///
+    /// With a positive contempt the side to move at the root avoids draws.
+    fn draw_score(&self) -> Evaluation {
+        if self.board.side_to_move() == self.root_color {
+            Evaluation::EQUALITY - self.contempt
+        } else {
+            Evaluation::EQUALITY + self.contempt
+        }
+    }
+
+    /// The moves one and two plies before `ply` for the continuation history
+    fn continuations(&self, ply: u8) -> Continuations {
+        let previous = |plies_back: u8| {
+            let entry = &self.stack[ply.checked_sub(plies_back)?];
+            Some(PieceTo {
+                piece: entry.moved_piece?,
+                to: entry.current_move.to(),
+            })
+        };
+
+        [previous(1), previous(2)]
+    }
+
+    /// Records into the tree dump if one is attached to this search. Without the `tree_dump`
+    /// feature this compiles to nothing.
+    #[inline(always)]
+    fn dump(&mut self, f: impl FnOnce(&mut TreeDump)) {
+        if TreeDump::enabled()
+            && let Some(tree_dump) = &mut self.tree_dump
+        {
+            f(tree_dump);
+        }
+    }
+
+    /// Static evaluation from the perspective of the side to move
+    fn evaluate(&self) -> Evaluation {
+        let evaluation = match self.board.side_to_move() {
+            Color::White => board_value(&self.board),
+            Color::Black => -board_value(&self.board),
+        };
+
+        evaluation + self.skill.eval_noise(self.board.hash())
+    }
+
     fn should_stop(&mut self, td: &mut ThreadData) -> bool {
+        // The node limit is checked at every node of the main and quiescence search against the
+        // nodes of this thread, which are a lower bound of the total. A single thread therefore
+        // stops at exactly the same node regardless of the polling interval below.
+        if let Some(max_nodes) = self.limits.nodes
+            && td.nodes_buffer.get(td.thread_id).load(Ordering::Relaxed) >= max_nodes
+        {
+            self.local_stop = true;
+            return true;
+        }
+
         self.calls_until_stop_check -= 1;
         if self.calls_until_stop_check > 0 {
             return self.local_stop;
         }
         self.calls_until_stop_check = 512;
 
+        self.check_ponderhit(td);
+
         if self.completed_depth == 0 {
             return false;
         }
 
-        if let Some(maximum) = self.clock.maximum {
-            if maximum < Instant::now() {
-                self.local_stop = true;
-            }
-        } else if td.stop_sync.stop.load(Ordering::Relaxed) {
+        let nodes = td.nodes_buffer.accumulate();
+
+        if let Some(maximum) = self.clock.maximum
+            && maximum < self.clock.now(nodes)
+        {
             self.local_stop = true;
         }
 
-        let nodes = td.nodes_buffer.accumulate();
+        if td.stop_sync.stop.load(Ordering::Relaxed) {
+            self.local_stop = true;
+        }
 
         if let Some(max_nodes) = self.limits.nodes
             && nodes >= max_nodes
@@ -388,7 +1221,31 @@ impl Search {
         self.local_stop
     }
 
-    fn info_string(&self, td: &mut ThreadData, depth: u8) -> Result<String, std::fmt::Error> {
+    /// Switches from pondering to the real clock once the opponent played the expected move
+    fn check_ponderhit(&mut self, td: &ThreadData) {
+        let TimeLimit::Ponder { limit } = &self.limits.time else {
+            return;
+        };
+
+        if !td.stop_sync.ponderhit.load(Ordering::Relaxed) {
+            return;
+        }
+
+        // The ponderhit clock starts at the ponderhit, so the time spent pondering does not count
+        // towards the thinking time of the move
+        if let Some(clock) = *td.stop_sync.ponderhit_clock.lock().unwrap() {
+            self.clock = clock;
+        }
+
+        self.limits.time = limit.as_ref().clone();
+    }
+
+    fn info_string(
+        &self,
+        td: &mut ThreadData,
+        depth: u8,
+        bound: Option<ValueType>,
+    ) -> Result<String, std::fmt::Error> {
         use std::fmt::Write;
 
         let mut output = String::with_capacity(120);
@@ -399,7 +1256,8 @@ impl Search {
 
         write!(
             output,
-            "info depth {depth} multipv {} score ",
+            "info depth {depth} seldepth {} multipv {} score ",
+            self.seldepth,
             self.pv_index + 1
         )?;
         if evaluation.is_mate() {
@@ -407,8 +1265,18 @@ impl Search {
         } else {
             write!(output, "cp {evaluation}")?;
         }
-        write!(output, " time {}", self.clock.start.elapsed().as_millis())?;
-        write!(output, " nodes {} pv", td.nodes_buffer.accumulate())?;
+        match bound {
+            Some(ValueType::Lowerbound) => write!(output, " lowerbound")?,
+            Some(ValueType::Upperbound) => write!(output, " upperbound")?,
+            _ => {}
+        }
+        let elapsed = self.start.elapsed().as_millis();
+        let nodes = td.nodes_buffer.accumulate();
+        let nps = nodes as u128 * 1000 / elapsed.max(1);
+
+        write!(output, " time {elapsed}")?;
+        write!(output, " nodes {nodes} nps {nps}")?;
+        write!(output, " hashfull {} tbhits 0 pv", td.tt.hashfull())?;
         for mov in pv.line() {
             write!(output, " {mov}")?;
         }
@@ -417,6 +1285,33 @@ impl Search {
     }
 }
 
+/// Lazy SMP: helper threads skip some depths based on their id, so that they search
+/// different depths than the main thread at the same time
+fn skip_depth(thread_id: u8, depth: u8, game_ply: u16) -> bool {
+    const SKIP_SIZE: [u16; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+    const SKIP_PHASE: [u16; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+
+    let index = thread_id as usize % SKIP_SIZE.len();
+    !((depth as u16 + game_ply + SKIP_PHASE[index]) / SKIP_SIZE[index]).is_multiple_of(2)
+}
+
+/// Depth reduction for a late move that always leaves at least one ply of remaining depth
+fn lmr_reduction<const PV: bool>(depth: u8, move_count: usize) -> u8 {
+    let depth_index = (depth as usize).min(internal::LMR_REDUCTIONS.len() - 1);
+    let move_index = move_count.min(internal::LMR_REDUCTIONS[0].len() - 1);
+
+    let mut reduction = internal::LMR_REDUCTIONS[depth_index][move_index];
+
+    // reduce less in PV nodes and more in non-PV nodes
+    if PV {
+        reduction = reduction.saturating_sub(1);
+    } else {
+        reduction += 1;
+    }
+
+    reduction.min(depth - 2)
+}
+
 fn get_value_type(score: Evaluation, alpha: Evaluation, beta: Evaluation) -> ValueType {
     if score <= alpha {
         ValueType::Upperbound
@@ -438,9 +1333,23 @@ fn tt_cutoff(entry: &Entry, alpha: Evaluation, beta: Evaluation) -> bool {
 #[derive(Debug, Clone)]
 pub struct RootMove {
     pub score: Evaluation,
+    pub previous_score: Evaluation,
+    /// Nodes spent searching this move over all iterations
+    pub nodes: u64,
     pub pv: PrincipleVariation,
 }
 
+impl RootMove {
+    pub fn new(m: Move) -> Self {
+        Self {
+            score: Evaluation::MIN,
+            previous_score: Evaluation::MIN,
+            nodes: 0,
+            pv: PrincipleVariation::from_root(m),
+        }
+    }
+}
+
 impl PartialEq for RootMove {
     fn eq(&self, other: &Self) -> bool {
         self.score == other.score
@@ -504,3 +1413,529 @@ impl NodeCountBuffer {
         }
     }
 }
+
+/// The outcome of the search of a single thread
+#[derive(Debug, Clone)]
+pub struct ThreadResult {
+    pub score: Evaluation,
+    pub completed_depth: u8,
+    pub pv: PrincipleVariation,
+}
+
+/// Collects the results of all threads at the end of a search
+pub struct ThreadResults {
+    inner: Mutex<Vec<Option<ThreadResult>>>,
+    cond_var: Condvar,
+}
+
+impl ThreadResults {
+    pub fn new(num_threads: u8) -> Self {
+        Self {
+            inner: Mutex::new(vec![None; num_threads as usize]),
+            cond_var: Condvar::new(),
+        }
+    }
+
+    pub fn publish(&self, thread_id: u8, result: ThreadResult) {
+        self.inner.lock().unwrap()[thread_id as usize] = Some(result);
+        self.cond_var.notify_all();
+    }
+
+    /// Waits for every thread to publish its result and returns the principal variation of
+    /// the thread whose best move received the most votes.
+    ///
+    /// Votes are weighted by the score and the completed depth of a thread. Proven mates are
+    /// always preferred. Threads that did not complete an iteration only take part if no thread
+    /// did.
+    pub fn vote(&self) -> PrincipleVariation {
+        let results = self
+            .cond_var
+            .wait_while(self.inner.lock().unwrap(), |results| {
+                results.iter().any(Option::is_none)
+            })
+            .unwrap();
+
+        let mut results: Vec<&ThreadResult> = results.iter().flatten().collect();
+        if results.iter().any(|r| r.completed_depth > 0) {
+            results.retain(|r| r.completed_depth > 0);
+        }
+
+        let min_score = results.iter().map(|r| r.score.val() as i64).min().unwrap();
+
+        let mut votes: Vec<(Move, i64)> = Vec::with_capacity(results.len());
+        for result in &results {
+            let weight =
+                (result.score.val() as i64 - min_score + 14) * result.completed_depth as i64;
+
+            match votes
+                .iter_mut()
+                .find(|(mv, _)| *mv == result.pv.best_move())
+            {
+                Some((_, vote)) => *vote += weight,
+                None => votes.push((result.pv.best_move(), weight)),
+            }
+        }
+
+        let votes_for = |result: &ThreadResult| {
+            votes
+                .iter()
+                .find(|(mv, _)| *mv == result.pv.best_move())
+                .map_or(0, |(_, vote)| *vote)
+        };
+        let is_win = |score: Evaluation| score.is_mate() && score.mate_num_ply() > 0;
+
+        let mut best = results[0];
+        for &result in &results[1..] {
+            let better = if is_win(best.score) {
+                // prefer the shortest mate
+                result.score > best.score
+            } else {
+                is_win(result.score) || votes_for(result) > votes_for(best)
+            };
+
+            if better {
+                best = result;
+            }
+        }
+
+        best.pv.clone()
+    }
+
+    pub fn clear(&self) {
+        for result in self.inner.lock().unwrap().iter_mut() {
+            *result = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::sync::atomic::Ordering;
+    use std::sync::mpsc::{Receiver, channel};
+
+    use web_time::{Duration, Instant};
+
+    use crate::board::Board;
+    use crate::clock::{Clock, TimeOptions};
+    use crate::evaluation::Evaluation;
+    use crate::pv::PrincipleVariation;
+    use crate::search::{
+        NodeCountBuffer, RootMove, Search, ThreadData, ThreadResult, ThreadResults, skip_depth,
+    };
+    use crate::skill::Skill;
+    use crate::stats::StatsBuffer;
+    use crate::threadpool::StopSync;
+    use crate::transposition::{MaybeUninitTT, ValueType};
+    use crate::types::chess_move::{Move, MoveFlag};
+    use crate::types::color::{Color, PerColor};
+    use crate::types::search_limits::{SearchLimits, TimeLimit};
+    use crate::types::square::Square;
+    use crate::uci::EngineMessage;
+
+    fn thread_data() -> ThreadData {
+        thread_data_with_receiver().0
+    }
+
+    /// Thread data whose engine messages, e.g. the info lines of the main thread, are kept
+    fn thread_data_with_receiver() -> (ThreadData, Receiver<EngineMessage>) {
+        let (engine_tx, engine_rx) = channel();
+        let tt = MaybeUninitTT::new(1);
+        let tt = unsafe {
+            tt.clear_chunk(0, 1);
+            tt.assume_init()
+        };
+
+        let td = ThreadData {
+            engine_tx,
+            tt,
+            stop_sync: Arc::new(StopSync::default()),
+            nodes_buffer: Arc::new(NodeCountBuffer::new(1)),
+            results: Arc::new(ThreadResults::new(1)),
+            stats: Arc::new(StatsBuffer::new(1)),
+            thread_id: 0,
+            ordering: Default::default(),
+            correction: Default::default(),
+        };
+
+        (td, engine_rx)
+    }
+
+    fn search(fen: &str, limits: SearchLimits, start: Instant) -> Search {
+        let board = Board::from_str(fen).unwrap();
+        let clock = Clock::new(
+            start,
+            &limits.time,
+            board.game_ply(),
+            board.side_to_move(),
+            &TimeOptions::default(),
+        );
+        let root_moves = board
+            .generate_moves()
+            .into_iter()
+            .map(RootMove::new)
+            .collect();
+
+        Search::new(board, limits, clock, root_moves, 1, Skill::default(), 0)
+    }
+
+    #[test]
+    fn test_ponderhit_after_long_ponder() {
+        let game_clock = TimeLimit::Dynamic {
+            time_left: PerColor::new([Duration::from_secs(60); 2]),
+            increment: PerColor::new([Duration::ZERO; 2]),
+            moves_to_go: None,
+        };
+        let limits = SearchLimits {
+            time: TimeLimit::Ponder {
+                limit: Box::new(game_clock.clone()),
+            },
+            ..Default::default()
+        };
+
+        let ponderhit = Instant::now();
+        let ponder_start = ponderhit - Duration::from_secs(5);
+        let mut search = search(Board::STARTING_POS_FEN, limits, ponder_start);
+
+        let td = thread_data();
+        let clock = Clock::new(
+            ponderhit,
+            &game_clock,
+            0,
+            Color::White,
+            &TimeOptions::default(),
+        );
+        *td.stop_sync.ponderhit_clock.lock().unwrap() = Some(clock);
+        td.stop_sync.ponderhit.store(true, Ordering::SeqCst);
+
+        search.check_ponderhit(&td);
+
+        // the time management scales the thinking time from the ponderhit on, as after a
+        // normal `go`, instead of counting the time spent pondering
+        assert_eq!(search.clock.start, ponderhit);
+        assert_eq!(search.start, ponder_start);
+        for scale in [0.5, 1.0, 1.5] {
+            assert_eq!(
+                search.clock.scaled_optimum(scale),
+                clock.scaled_optimum(scale)
+            );
+        }
+        assert!(search.clock.scaled_optimum(0.5).unwrap() - ponderhit > Duration::from_millis(500));
+        assert_eq!(search.limits.time, game_clock);
+    }
+
+    #[test]
+    fn test_info_string() {
+        let limits = SearchLimits {
+            time: TimeLimit::Ponder {
+                limit: Box::new(TimeLimit::Infinite),
+            },
+            ..Default::default()
+        };
+        let ponder_start = Instant::now() - Duration::from_secs(5);
+        let mut search = search(Board::STARTING_POS_FEN, limits, ponder_start);
+
+        // the time is reported from the `go` command on, even after a ponderhit
+        let mut td = thread_data();
+        let clock = Clock::new(
+            Instant::now(),
+            &TimeLimit::Infinite,
+            0,
+            Color::White,
+            &TimeOptions::default(),
+        );
+        *td.stop_sync.ponderhit_clock.lock().unwrap() = Some(clock);
+        td.stop_sync.ponderhit.store(true, Ordering::SeqCst);
+        search.check_ponderhit(&td);
+
+        let mv = search.root_moves[0].pv.best_move();
+        search.root_moves[0].score = Evaluation::EQUALITY + 35;
+        search.seldepth = 9;
+        td.nodes_buffer.get(0).store(50_000, Ordering::Relaxed);
+
+        let output = search
+            .info_string(&mut td, 7, Some(ValueType::Lowerbound))
+            .unwrap();
+
+        let rest = output
+            .strip_prefix("info depth 7 seldepth 9 multipv 1 score cp 35 lowerbound time ")
+            .unwrap();
+        let (time, rest) = rest.split_once(' ').unwrap();
+        let time: u128 = time.parse().unwrap();
+        assert!((5000..60_000).contains(&time));
+
+        let nps = 50_000 * 1000 / time;
+        assert_eq!(
+            rest,
+            format!("nodes 50000 nps {nps} hashfull 0 tbhits 0 pv {mv}")
+        );
+
+        search.root_moves[0].score = Evaluation::mate_in(3);
+        let output = search
+            .info_string(&mut td, 7, Some(ValueType::Upperbound))
+            .unwrap();
+        assert!(output.contains(" score mate 2 upperbound time "));
+    }
+
+    fn vote(results: &[(Move, Evaluation, u8)]) -> Move {
+        let thread_results = ThreadResults::new(results.len() as u8);
+        for (thread_id, &(mv, score, completed_depth)) in results.iter().enumerate() {
+            thread_results.publish(
+                thread_id as u8,
+                ThreadResult {
+                    score,
+                    completed_depth,
+                    pv: PrincipleVariation::from_root(mv),
+                },
+            );
+        }
+
+        thread_results.vote().best_move()
+    }
+
+    #[test]
+    fn test_vote() {
+        let e2e4 = Move::new(Square::E2, Square::E4, MoveFlag::DoublePawnPush);
+        let d2d4 = Move::new(Square::D2, Square::D4, MoveFlag::DoublePawnPush);
+        let g1f3 = Move::new(Square::G1, Square::F3, MoveFlag::Normal);
+        let cp = |score| Evaluation::EQUALITY + score;
+
+        assert_eq!(vote(&[(e2e4, cp(20), 10)]), e2e4);
+
+        // deeper results weigh more
+        assert_eq!(vote(&[(e2e4, cp(20), 6), (d2d4, cp(20), 12)]), d2d4);
+
+        // better scores weigh more
+        assert_eq!(vote(&[(e2e4, cp(20), 10), (d2d4, cp(60), 10)]), d2d4);
+
+        // votes for the same move add up
+        assert_eq!(
+            vote(&[(e2e4, cp(20), 12), (d2d4, cp(20), 10), (d2d4, cp(20), 10)]),
+            d2d4
+        );
+
+        // a proven win beats any number of votes, the shortest mate beats longer ones
+        let mate_in = |ply| Evaluation::mate_in(ply);
+        assert_eq!(
+            vote(&[
+                (e2e4, cp(300), 20),
+                (e2e4, cp(300), 20),
+                (g1f3, mate_in(9), 8)
+            ]),
+            g1f3
+        );
+        assert_eq!(
+            vote(&[
+                (e2e4, mate_in(9), 12),
+                (g1f3, mate_in(5), 8),
+                (d2d4, mate_in(7), 10)
+            ]),
+            g1f3
+        );
+
+        // the score of a thread without a completed iteration is not trusted, not even a mate
+        assert_eq!(vote(&[(e2e4, cp(20), 1), (g1f3, mate_in(3), 0)]), e2e4);
+        assert_eq!(vote(&[(g1f3, mate_in(3), 0), (e2e4, cp(20), 1)]), e2e4);
+        assert_eq!(vote(&[(e2e4, cp(20), 0), (d2d4, cp(20), 0)]), e2e4);
+    }
+
+    #[test]
+    fn test_skip_depth() {
+        for thread_id in 1..=40 {
+            for game_ply in 0..4 {
+                let skipped: Vec<bool> = (1..64)
+                    .map(|depth| skip_depth(thread_id, depth, game_ply))
+                    .collect();
+
+                // every helper searches and skips depths regularly
+                assert!(skipped.windows(8).all(|w| w.contains(&false)));
+                assert!(skipped.windows(8).all(|w| w.contains(&true)));
+            }
+        }
+
+        // the helpers are spread over neighbouring depths instead of all searching the same
+        for depth in 2..64 {
+            let skipping = (1..8).filter(|&id| skip_depth(id, depth, 0)).count();
+            assert!(0 < skipping && skipping < 7, "depth {depth}");
+        }
+
+        // each move shifts the pattern by one depth
+        for thread_id in 1..20 {
+            for depth in 1..63 {
+                assert_eq!(
+                    skip_depth(thread_id, depth, 1),
+                    skip_depth(thread_id, depth + 1, 0)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_extensions_are_bounded() {
+        // positions full of checks and single forced replies whose TT moves are extended
+        for fen in [
+            "7k/6pp/8/8/8/8/q5PP/3QR2K w - - 0 1",
+            "8/8/8/3k4/8/8/2Q5/K7 w - - 0 1",
+            "2r3k1/1q3ppp/8/8/8/8/1Q3PPP/2R3K1 w - - 0 1",
+        ] {
+            let depth = 12;
+            let limits = SearchLimits {
+                time: TimeLimit::Infinite,
+                depth: Some(depth),
+                ..Default::default()
+            };
+            let mut search = search(fen, limits, Instant::now());
+            let mut td = thread_data();
+            search.iterative_deepening(&mut td, false);
+
+            // without a limit the extended lines would run into the `MAX_PLY` guard
+            assert_eq!(search.completed_depth, depth);
+            assert!(
+                search.seldepth <= 4 * depth,
+                "{fen}: seldepth {}",
+                search.seldepth
+            );
+        }
+    }
+
+    #[test]
+    fn test_node_limit_is_deterministic() {
+        let fen = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+        // the limit is also checked before the first iteration completed and, with 20003 nodes,
+        // in the middle of a quiescence search
+        for max_nodes in [10, 20_000, 20_003] {
+            let run = || {
+                let limits = SearchLimits {
+                    time: TimeLimit::Infinite,
+                    nodes: Some(max_nodes),
+                    ..Default::default()
+                };
+                let mut td = thread_data();
+                let best_move = search(fen, limits, Instant::now()).run(&mut td, false);
+
+                let pv = td.results.vote().line().to_vec();
+                (td.nodes_buffer.accumulate(), best_move, pv)
+            };
+
+            let (nodes, best_move, pv) = run();
+            assert_eq!(nodes, max_nodes);
+            assert_ne!(best_move, Move::NULL);
+            assert_eq!(run(), (nodes, best_move, pv));
+        }
+    }
+
+    /// Runs a `go mate` search as main thread and returns whether it reported no mate and the
+    /// last reported principal variation
+    fn mate_search(fen: &str, mate: u8) -> (Search, bool, String) {
+        let limits = SearchLimits {
+            time: TimeLimit::Infinite,
+            mate: Some(mate),
+            ..Default::default()
+        };
+        let mut search = search(fen, limits, Instant::now());
+        let (mut td, engine_rx) = thread_data_with_receiver();
+        search.iterative_deepening(&mut td, true);
+
+        let responses: Vec<String> = engine_rx
+            .try_iter()
+            .filter_map(|message| match message {
+                EngineMessage::Response(response) => Some(response),
+                _ => None,
+            })
+            .collect();
+        let no_mate = responses.iter().any(|r| r == "info string no mate found");
+        let last_pv = responses
+            .iter()
+            .rev()
+            .find_map(|r| r.split_once(" pv ").map(|(_, pv)| pv.to_owned()))
+            .unwrap_or_default();
+
+        (search, no_mate, last_pv)
+    }
+
+    #[test]
+    fn test_mate_search_stops_at_mate() {
+        // 1. Ra6 bxa6 2. b7#
+        let (search, no_mate, last_pv) = mate_search("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1", 5);
+
+        assert!(!no_mate);
+        assert_eq!(search.root_moves[0].score, Evaluation::mate_in(3));
+        assert_eq!(
+            search.root_moves[0].pv.best_move(),
+            Move::new(Square::A1, Square::A6, MoveFlag::Normal)
+        );
+        // a mate in 5 could take up to 9 plies, but the search stops once the mate is proven
+        assert!(search.completed_depth <= 3);
+
+        // the reported line includes the mating move found by the quiescence search
+        let mut board = search.board.clone();
+        for &mv in search.root_moves[0].pv.line() {
+            board.apply_move(mv);
+        }
+        assert!(board.generate_moves().is_empty());
+        assert!(!board.checkers().is_empty());
+        assert_eq!(last_pv, "a1a6 b7a6 b6b7");
+    }
+
+    #[test]
+    fn test_mate_search_reports_no_mate() {
+        let (search, no_mate, _) = mate_search(Board::STARTING_POS_FEN, 2);
+
+        assert!(no_mate);
+        assert!(!search.root_moves[0].score.is_mate());
+        // a mate in 2 is at most 3 plies deep
+        assert_eq!(search.completed_depth, 3);
+    }
+
+    #[test]
+    fn test_quiescence_skips_losing_captures() {
+        let qsearch_nodes = |fen| {
+            let mut search = search(fen, SearchLimits::default(), Instant::now());
+            let mut td = thread_data();
+            search.quiescence::<true>(&mut td, Evaluation::MIN, Evaluation::MAX, 0, false);
+            td.nodes_buffer.accumulate()
+        };
+
+        // the queen can only capture the pawn on e5, which is defended by the pawn on d6
+        assert_eq!(qsearch_nodes("1k6/8/3p4/2r1p3/8/8/8/K3Q3 w - - 0 1"), 1);
+
+        // the pawn on b4 can win the rook on c5
+        assert!(qsearch_nodes("1k6/8/3p4/2r1p3/1P6/8/8/K3Q3 w - - 0 1") > 1);
+    }
+
+    #[test]
+    fn test_draw_score_depends_on_root_color() {
+        for fen in [
+            Board::STARTING_POS_FEN,
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+        ] {
+            let mut search = search(fen, SearchLimits::default(), Instant::now());
+            search.contempt = 20;
+
+            // the side to move at the root avoids draws, its opponent welcomes them
+            assert_eq!(search.draw_score(), Evaluation::EQUALITY - 20);
+            let mv = search.board.generate_moves()[0];
+            search.board.apply_move(mv);
+            assert_eq!(search.draw_score(), Evaluation::EQUALITY + 20);
+            search.board.undo_move();
+
+            search.contempt = -20;
+            assert_eq!(search.draw_score(), Evaluation::EQUALITY + 20);
+        }
+    }
+
+    #[test]
+    fn test_quiescence_scores_stalemate_as_draw() {
+        // black is stalemated at the horizon despite being a queen down
+        let mut search = search(
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+            SearchLimits::default(),
+            Instant::now(),
+        );
+        let mut td = thread_data();
+        let score = search.quiescence::<true>(&mut td, Evaluation::MIN, Evaluation::MAX, 0, true);
+        assert_eq!(score, Evaluation::EQUALITY);
+    }
+}
@@ -0,0 +1,117 @@
+use std::ops::{Index, IndexMut};
+
+use crate::evaluation::Evaluation;
+use crate::pv::{MAX_PLY, PrincipleVariation};
+use crate::types::chess_move::Move;
+use crate::types::piece::Piece;
+
+/// Search state of a single ply
+#[derive(Debug, Clone)]
+pub struct StackEntry {
+    /// Static evaluation of the position, `None` when in check
+    pub static_eval: Option<Evaluation>,
+    /// Move currently searched from this ply, `Move::NULL` for a null move
+    pub current_move: Move,
+    /// Piece moved by `current_move`
+    pub moved_piece: Option<Piece>,
+    /// Two quiet moves that recently caused a beta cutoff at this ply
+    pub killers: [Move; 2],
+    /// Move skipped by the search of this ply, `Move::NULL` if none
+    pub excluded_move: Move,
+    pub in_check: bool,
+    /// Principal variation found at this ply
+    pub pv: PrincipleVariation,
+}
+
+impl Default for StackEntry {
+    fn default() -> Self {
+        Self {
+            static_eval: None,
+            current_move: Move::NULL,
+            moved_piece: None,
+            killers: [Move::NULL; 2],
+            excluded_move: Move::NULL,
+            in_check: false,
+            pv: PrincipleVariation::default(),
+        }
+    }
+}
+
+/// Preallocated per ply state of the search, indexed by ply
+#[derive(Debug, Clone)]
+pub struct SearchStack {
+    entries: Box<[StackEntry; MAX_PLY]>,
+}
+
+impl Default for SearchStack {
+    fn default() -> Self {
+        Self {
+            entries: Box::new(std::array::from_fn(|_| StackEntry::default())),
+        }
+    }
+}
+
+impl SearchStack {
+    /// Sets the principal variation of `ply` to `mv` followed by the one of the next ply
+    pub fn update_pv(&mut self, ply: u8, mv: Move) {
+        let (current, child) = self.entries.split_at_mut(ply as usize + 1);
+        current[ply as usize].pv.load_from(mv, &child[0].pv);
+    }
+
+    /// Remembers the quiet move `mv` that caused a beta cutoff at `ply`
+    pub fn update_killers(&mut self, ply: u8, mv: Move) {
+        let killers = &mut self.entries[ply as usize].killers;
+        if killers[0] != mv {
+            killers[1] = killers[0];
+            killers[0] = mv;
+        }
+    }
+}
+
+impl Index<u8> for SearchStack {
+    type Output = StackEntry;
+
+    fn index(&self, ply: u8) -> &Self::Output {
+        &self.entries[ply as usize]
+    }
+}
+
+impl IndexMut<u8> for SearchStack {
+    fn index_mut(&mut self, ply: u8) -> &mut Self::Output {
+        &mut self.entries[ply as usize]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::search::stack::SearchStack;
+    use crate::types::chess_move::{Move, MoveFlag};
+    use crate::types::square::Square;
+
+    #[test]
+    fn test_update_pv() {
+        let e2e4 = Move::new(Square::E2, Square::E4, MoveFlag::DoublePawnPush);
+        let e7e5 = Move::new(Square::E7, Square::E5, MoveFlag::DoublePawnPush);
+
+        let mut stack = SearchStack::default();
+        stack.update_pv(1, e7e5);
+        stack.update_pv(0, e2e4);
+
+        assert_eq!(stack[0].pv.line(), &[e2e4, e7e5]);
+        assert_eq!(stack[1].pv.line(), &[e7e5]);
+    }
+
+    #[test]
+    fn test_update_killers() {
+        let g1f3 = Move::new(Square::G1, Square::F3, MoveFlag::Normal);
+        let b1c3 = Move::new(Square::B1, Square::C3, MoveFlag::Normal);
+
+        let mut stack = SearchStack::default();
+        stack.update_killers(2, g1f3);
+        stack.update_killers(2, g1f3);
+        assert_eq!(stack[2].killers, [g1f3, Move::NULL]);
+
+        stack.update_killers(2, b1c3);
+        assert_eq!(stack[2].killers, [b1c3, g1f3]);
+    }
+}
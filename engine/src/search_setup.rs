@@ -0,0 +1,64 @@
+use std::{
+    sync::{Arc, mpsc::Sender},
+    time::Instant,
+};
+
+use engine::{
+    board::Board,
+    clock::{Clock, TimeOptions},
+    search::{NodeCountBuffer, RootMove, Search, ThreadData, ThreadResults},
+    skill::Skill,
+    stats::StatsBuffer,
+    threadpool::StopSync,
+    transposition::MaybeUninitTT,
+    types::search_limits::{SearchLimits, TimeLimit},
+    uci::EngineMessage,
+};
+
+/// Thread data for searching on the current thread outside of the thread pool, with a cleared
+/// 16 MB transposition table
+pub(crate) fn single_thread_data(engine_tx: Sender<EngineMessage>) -> ThreadData {
+    let tt = MaybeUninitTT::new(16);
+    let tt = unsafe {
+        tt.clear_chunk(0, 1);
+        tt.assume_init()
+    };
+
+    ThreadData {
+        engine_tx,
+        tt,
+        stop_sync: Arc::new(StopSync::default()),
+        nodes_buffer: Arc::new(NodeCountBuffer::new(1)),
+        results: Arc::new(ThreadResults::new(1)),
+        stats: Arc::new(StatsBuffer::new(1)),
+        thread_id: 0,
+        ordering: Default::default(),
+        correction: Default::default(),
+    }
+}
+
+/// Search of `board` up to `depth` without a time limit. The root moves are empty if the side
+/// to move has no legal moves.
+pub(crate) fn fixed_depth_search(board: Board, depth: u8) -> Search {
+    let limits = SearchLimits {
+        time: TimeLimit::Infinite,
+        depth: Some(depth),
+        ..Default::default()
+    };
+
+    let clock = Clock::new(
+        Instant::now(),
+        &limits.time,
+        board.game_ply(),
+        board.side_to_move(),
+        &TimeOptions::default(),
+    );
+
+    let root_moves: Vec<RootMove> = board
+        .generate_moves()
+        .into_iter()
+        .map(RootMove::new)
+        .collect();
+
+    Search::new(board, limits, clock, root_moves, 1, Skill::default(), 0)
+}
@@ -0,0 +1,181 @@
+use crate::board::Board;
+use crate::evaluation::hce::raw_piece_value;
+use crate::movegen::attacks::{bishop_attacks, rook_attacks};
+use crate::types::chess_move::{Move, MoveFlag};
+use crate::types::piece::PieceType;
+
+/// Static exchange evaluation
+///
+/// Returns whether the sequence of captures on the target square of `mv` gains at least
+/// `threshold` centipawns for the side to move, assuming both sides always recapture with
+/// their least valuable attacker. Attackers revealed by captures (x-rays) are taken into account.
+pub fn see(board: &Board, mv: Move, threshold: i16) -> bool {
+    if mv.flag() == MoveFlag::Castling {
+        return threshold <= 0;
+    }
+
+    let from = mv.from();
+    let to = mv.to();
+
+    let captured = match mv.flag() {
+        MoveFlag::EnPassant => PieceType::Pawn,
+        _ => board.piece_at(to).map_or(PieceType::King, |p| p.ty()),
+    };
+
+    // the value we are still up after the opponent recaptured
+    let mut swap = raw_piece_value(captured) - threshold;
+    if swap < 0 {
+        return false;
+    }
+
+    let moved = board
+        .piece_at(from)
+        .expect("source square should not be empty")
+        .ty();
+
+    // the value we are still up if the opponent does not recapture
+    swap = raw_piece_value(moved) - swap;
+    if swap <= 0 {
+        return true;
+    }
+
+    let mut occupied = board.combined();
+    occupied ^= from;
+    occupied ^= to;
+    if mv.flag() == MoveFlag::EnPassant {
+        occupied ^= to.forward(!board.side_to_move());
+    }
+
+    let bishops = board.pieces(PieceType::Bishop) | board.pieces(PieceType::Queen);
+    let rooks = board.pieces(PieceType::Rook) | board.pieces(PieceType::Queen);
+
+    let mut side = board.side_to_move();
+    let mut attackers = board.attackers_to(to, occupied);
+
+    // 1 if the side to move wins the exchange, 0 otherwise
+    let mut result = 1;
+
+    loop {
+        side = !side;
+        attackers &= occupied;
+
+        let side_attackers = attackers & board.occupancies(side);
+        if side_attackers.is_empty() {
+            break;
+        }
+
+        result ^= 1;
+
+        let Some((attacker, attacker_bb)) = [
+            PieceType::Pawn,
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Rook,
+            PieceType::Queen,
+            PieceType::King,
+        ]
+        .into_iter()
+        .map(|ty| (ty, side_attackers & board.pieces(ty)))
+        .find(|(_, bb)| !bb.is_empty()) else {
+            unreachable!("there should be an attacker left");
+        };
+
+        if attacker == PieceType::King {
+            // the king can only recapture if the opponent has no attackers left
+            return if (attackers & !board.occupancies(side)).is_empty() {
+                result != 0
+            } else {
+                result == 0
+            };
+        }
+
+        swap = raw_piece_value(attacker) - swap;
+        if swap < result {
+            break;
+        }
+
+        occupied ^= attacker_bb.lsb();
+
+        // add attackers revealed behind the capturing piece
+        match attacker {
+            PieceType::Pawn | PieceType::Bishop => {
+                attackers |= bishop_attacks(to, occupied) & bishops;
+            }
+            PieceType::Rook => {
+                attackers |= rook_attacks(to, occupied) & rooks;
+            }
+            PieceType::Queen => {
+                attackers |=
+                    (bishop_attacks(to, occupied) & bishops) | (rook_attacks(to, occupied) & rooks);
+            }
+            PieceType::Knight | PieceType::King => {}
+        }
+    }
+
+    result != 0
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::board::Board;
+    use crate::see::see;
+    use crate::types::chess_move::Move;
+    use crate::types::square::Square;
+
+    fn find_move(board: &Board, from: Square, to: Square) -> Move {
+        board
+            .generate_moves()
+            .into_iter()
+            .find(|m| m.from() == from && m.to() == to)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_undefended_capture() {
+        let board = Board::from_str("1k6/8/8/4p3/8/8/8/K3R3 w - - 0 1").unwrap();
+        let mv = find_move(&board, Square::E1, Square::E5);
+
+        assert!(see(&board, mv, 0));
+        assert!(see(&board, mv, 100));
+        assert!(!see(&board, mv, 101));
+    }
+
+    #[test]
+    fn test_queen_takes_defended_pawn() {
+        let board = Board::from_str("1k6/8/3p4/4p3/8/8/8/K3Q3 w - - 0 1").unwrap();
+        let mv = find_move(&board, Square::E1, Square::E5);
+
+        assert!(!see(&board, mv, 0));
+        assert!(see(&board, mv, -800));
+    }
+
+    #[test]
+    fn test_xray_recapture() {
+        // the rook on e1 backs up the capture of the rook on e2
+        let board = Board::from_str("1k2r3/8/8/4p3/8/8/4R3/K3R3 w - - 0 1").unwrap();
+        let mv = find_move(&board, Square::E2, Square::E5);
+        assert!(see(&board, mv, 0));
+        assert!(!see(&board, mv, 101));
+
+        let board = Board::from_str("1k2r3/8/8/4p3/8/8/4R3/K7 w - - 0 1").unwrap();
+        let mv = find_move(&board, Square::E2, Square::E5);
+        assert!(!see(&board, mv, 0));
+    }
+
+    #[test]
+    fn test_quiet_move_to_attacked_square() {
+        let board = Board::from_str("1k6/8/3p4/8/8/8/8/K3N3 w - - 0 1").unwrap();
+        let mv = find_move(&board, Square::E1, Square::F3);
+        assert!(see(&board, mv, 0));
+
+        let board = Board::from_str("1k6/8/8/3p4/8/8/8/K1N5 w - - 0 1").unwrap();
+        let mv = find_move(&board, Square::C1, Square::E2);
+        assert!(see(&board, mv, 0));
+
+        let board = Board::from_str("1k6/8/8/8/3p4/8/2N5/K7 w - - 0 1").unwrap();
+        let mv = find_move(&board, Square::C2, Square::E3);
+        assert!(!see(&board, mv, 0));
+    }
+}
@@ -0,0 +1,246 @@
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::evaluation::Evaluation;
+use crate::search::RootMove;
+use crate::types::chess_move::Move;
+
+/// Skill level at which the engine plays at full strength
+pub const MAX_SKILL_LEVEL: u8 = 20;
+
+/// Range of the `UCI_Elo` option
+pub const MIN_ELO: u16 = 1320;
+pub const MAX_ELO: u16 = 3190;
+
+/// Minimum number of root moves searched to choose a weaker move from
+const MIN_SKILL_MULTIPV: u8 = 4;
+
+/// Seed used instead of the system time for reproducible searches
+const DETERMINISTIC_SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+/// Weakens the engine by limiting the search and deliberately choosing sub-optimal moves.
+///
+/// Inspired by: https://github.com/official-stockfish/Stockfish/blob/sf_16/src/search.cpp#L1857
+#[derive(Debug, Clone, Copy)]
+pub struct Skill {
+    level: f64,
+    /// Fixed seed for choosing the weaker move, the system time is used if unset
+    seed: Option<u64>,
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Self {
+            level: MAX_SKILL_LEVEL as f64,
+            seed: None,
+        }
+    }
+}
+
+impl Skill {
+    /// `UCI_Elo` takes precedence over `Skill Level` if `UCI_LimitStrength` is set
+    pub fn new(skill_level: u8, limit_strength: bool, elo: u16) -> Self {
+        let level = if limit_strength {
+            // calibrated by Stockfish against a pool of engines, see the link above
+            let e = (elo.clamp(MIN_ELO, MAX_ELO) - MIN_ELO) as f64 / (MAX_ELO - MIN_ELO) as f64;
+            (((37.2473 * e - 40.8525) * e + 22.2944) * e - 0.311438).clamp(0.0, 19.0)
+        } else {
+            skill_level.min(MAX_SKILL_LEVEL) as f64
+        };
+
+        Self { level, seed: None }
+    }
+
+    /// Always chooses the same move for the same search results
+    pub fn deterministic(self) -> Self {
+        Self {
+            seed: Some(DETERMINISTIC_SEED),
+            ..self
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.level < MAX_SKILL_LEVEL as f64
+    }
+
+    /// Number of principal variations needed to choose a weaker move from
+    pub fn multipv(&self, multipv: u8) -> u8 {
+        if self.enabled() {
+            multipv.max(MIN_SKILL_MULTIPV)
+        } else {
+            multipv
+        }
+    }
+
+    pub fn depth_limit(&self) -> Option<u8> {
+        self.enabled().then(|| 1 + self.level as u8)
+    }
+
+    pub fn nodes_limit(&self) -> Option<u64> {
+        self.enabled().then(|| {
+            let level = self.level as u64 + 1;
+            1000 * level * level
+        })
+    }
+
+    /// Deterministic noise in centipawns added to the static evaluation of a position
+    pub fn eval_noise(&self, hash: u64) -> i16 {
+        if !self.enabled() {
+            return 0;
+        }
+
+        let amplitude = ((MAX_SKILL_LEVEL as f64 - self.level) * 5.0) as u64;
+        let hash = hash.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32;
+        ((hash % (2 * amplitude + 1)) as i64 - amplitude as i64) as i16
+    }
+
+    /// Chooses one of the first `multipv` root moves, giving weaker moves a random bonus that
+    /// grows the lower the skill level is
+    pub fn pick_move(&self, root_moves: &[RootMove], multipv: usize) -> Move {
+        let seed = self.seed.unwrap_or_else(random_seed);
+        self.pick_move_with_seed(root_moves, multipv, seed)
+    }
+
+    fn pick_move_with_seed(&self, root_moves: &[RootMove], multipv: usize, seed: u64) -> Move {
+        let mut rng = Xorshift::new(seed);
+
+        // moves of an unfinished iteration might not have a score yet
+        let candidates: Vec<&RootMove> = root_moves
+            .iter()
+            .take(multipv.max(1))
+            .filter(|root_move| root_move.score != Evaluation::MIN)
+            .collect();
+
+        let Some(best) = candidates.first() else {
+            return root_moves[0].pv.best_move();
+        };
+
+        let top_score = best.score.val() as i64;
+        let worst_score = candidates.last().unwrap().score.val() as i64;
+        let delta = (top_score - worst_score).min(100);
+        let weakness = 120 - 2 * self.level as i64;
+
+        let mut best_move = best.pv.best_move();
+        let mut max_score = i64::MIN;
+        for candidate in candidates {
+            let score = candidate.score.val() as i64;
+            let push = (weakness * (top_score - score)
+                + delta * (rng.next() % weakness as u64) as i64)
+                / 128;
+
+            if score + push >= max_score {
+                max_score = score + push;
+                best_move = candidate.pv.best_move();
+            }
+        }
+
+        best_move
+    }
+}
+
+fn random_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+
+    // the state of a xorshift generator must not be zero
+    nanos | 1
+}
+
+struct Xorshift {
+    state: u64,
+}
+
+impl Xorshift {
+    fn new(seed: u64) -> Self {
+        Self { state: seed.max(1) }
+    }
+
+    fn next(&mut self) -> u64 {
+        // See: https://en.wikipedia.org/wiki/Xorshift
+        let mut number = self.state;
+        number ^= number << 13;
+        number ^= number >> 7;
+        number ^= number << 17;
+
+        self.state = number;
+        number
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::board::Board;
+    use crate::evaluation::Evaluation;
+    use crate::search::RootMove;
+    use crate::skill::{MAX_ELO, MIN_ELO, Skill};
+
+    #[test]
+    fn test_full_strength_is_disabled() {
+        assert!(!Skill::default().enabled());
+        assert!(!Skill::new(20, false, MIN_ELO).enabled());
+        assert!(Skill::new(20, true, MIN_ELO).enabled());
+        assert!(Skill::new(5, false, MAX_ELO).enabled());
+
+        assert_eq!(Skill::default().multipv(1), 1);
+        assert_eq!(Skill::new(5, false, MAX_ELO).multipv(1), 4);
+    }
+
+    #[test]
+    fn test_elo_increases_depth() {
+        let weak = Skill::new(20, true, MIN_ELO).depth_limit().unwrap();
+        let strong = Skill::new(20, true, 2800).depth_limit().unwrap();
+        assert!(weak < strong);
+    }
+
+    #[test]
+    fn test_picks_searched_move() {
+        let board = Board::default();
+        let mut root_moves: Vec<RootMove> = board
+            .generate_moves()
+            .into_iter()
+            .map(RootMove::new)
+            .collect();
+        for (i, root_move) in root_moves.iter_mut().take(4).enumerate() {
+            root_move.score = Evaluation::EQUALITY - 30 * i as i16;
+        }
+
+        let skill = Skill::new(0, false, MIN_ELO);
+        let mut picked = vec![];
+        for seed in 1..100 {
+            let mv = skill.pick_move_with_seed(&root_moves, 4, seed);
+            assert!(root_moves[..4].iter().any(|r| r.pv.best_move() == mv));
+            picked.push(mv);
+        }
+
+        // the weakest skill level should not always play the best move
+        assert!(picked.iter().any(|&mv| mv != root_moves[0].pv.best_move()));
+    }
+
+    #[test]
+    fn test_deterministic_pick() {
+        let board = Board::default();
+        let mut root_moves: Vec<RootMove> = board
+            .generate_moves()
+            .into_iter()
+            .map(RootMove::new)
+            .collect();
+        for (i, root_move) in root_moves.iter_mut().take(4).enumerate() {
+            root_move.score = Evaluation::EQUALITY - 10 * i as i16;
+        }
+
+        let skill = Skill::new(0, false, MIN_ELO).deterministic();
+        let mv = skill.pick_move(&root_moves, 4);
+        for _ in 0..10 {
+            assert_eq!(skill.pick_move(&root_moves, 4), mv);
+        }
+    }
+
+    #[test]
+    fn test_eval_noise_is_bounded() {
+        let skill = Skill::new(0, false, MIN_ELO);
+        for hash in 0..1000 {
+            assert!(skill.eval_noise(hash).abs() <= 100);
+        }
+        assert_eq!(Skill::default().eval_noise(42), 0);
+    }
+}
@@ -0,0 +1,208 @@
+//! Search statistics for tuning, only collected with the `stats` feature.
+//!
+//! Without the feature every method is an empty function, so the calls in the search compile to
+//! nothing.
+
+#[cfg(feature = "stats")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Snapshot of the statistics of one or more threads
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SearchStats {
+    pub nodes: u64,
+    pub quiescence_nodes: u64,
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+    pub tt_cutoffs: u64,
+    pub beta_cutoffs: u64,
+    pub first_move_cutoffs: u64,
+    /// Sum of the move numbers, counting from one, of all moves that caused a beta cutoff
+    pub cutoff_move_number_sum: u64,
+}
+
+impl SearchStats {
+    /// Human readable summary, e.g. for an `info string`
+    pub fn summary(&self) -> String {
+        fn percent(part: u64, total: u64) -> f64 {
+            part as f64 * 100.0 / total.max(1) as f64
+        }
+
+        format!(
+            "stats tt probes {} hits {:.1}% cutoffs {} beta cutoffs {} first move {:.1}% \
+             avg cutoff move {:.2} qsearch nodes {:.1}%",
+            self.tt_probes,
+            percent(self.tt_hits, self.tt_probes),
+            self.tt_cutoffs,
+            self.beta_cutoffs,
+            percent(self.first_move_cutoffs, self.beta_cutoffs),
+            self.cutoff_move_number_sum as f64 / self.beta_cutoffs.max(1) as f64,
+            percent(self.quiescence_nodes, self.nodes),
+        )
+    }
+}
+
+impl std::ops::AddAssign for SearchStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.nodes += rhs.nodes;
+        self.quiescence_nodes += rhs.quiescence_nodes;
+        self.tt_probes += rhs.tt_probes;
+        self.tt_hits += rhs.tt_hits;
+        self.tt_cutoffs += rhs.tt_cutoffs;
+        self.beta_cutoffs += rhs.beta_cutoffs;
+        self.first_move_cutoffs += rhs.first_move_cutoffs;
+        self.cutoff_move_number_sum += rhs.cutoff_move_number_sum;
+    }
+}
+
+#[cfg(feature = "stats")]
+#[derive(Debug, Default)]
+struct ThreadStats {
+    nodes: AtomicU64,
+    quiescence_nodes: AtomicU64,
+    tt_probes: AtomicU64,
+    tt_hits: AtomicU64,
+    tt_cutoffs: AtomicU64,
+    beta_cutoffs: AtomicU64,
+    first_move_cutoffs: AtomicU64,
+    cutoff_move_number_sum: AtomicU64,
+}
+
+#[cfg(feature = "stats")]
+impl ThreadStats {
+    fn snapshot(&self) -> SearchStats {
+        SearchStats {
+            nodes: self.nodes.load(Ordering::Relaxed),
+            quiescence_nodes: self.quiescence_nodes.load(Ordering::Relaxed),
+            tt_probes: self.tt_probes.load(Ordering::Relaxed),
+            tt_hits: self.tt_hits.load(Ordering::Relaxed),
+            tt_cutoffs: self.tt_cutoffs.load(Ordering::Relaxed),
+            beta_cutoffs: self.beta_cutoffs.load(Ordering::Relaxed),
+            first_move_cutoffs: self.first_move_cutoffs.load(Ordering::Relaxed),
+            cutoff_move_number_sum: self.cutoff_move_number_sum.load(Ordering::Relaxed),
+        }
+    }
+
+    fn clear(&self) {
+        for counter in [
+            &self.nodes,
+            &self.quiescence_nodes,
+            &self.tt_probes,
+            &self.tt_hits,
+            &self.tt_cutoffs,
+            &self.beta_cutoffs,
+            &self.first_move_cutoffs,
+            &self.cutoff_move_number_sum,
+        ] {
+            counter.store(0, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(feature = "stats")]
+fn increment(counter: &AtomicU64, value: u64) {
+    counter.fetch_add(value, Ordering::Relaxed);
+}
+
+/// Per thread search statistics, each thread only writes to its own slot
+#[derive(Debug)]
+pub struct StatsBuffer {
+    #[cfg(feature = "stats")]
+    inner: Vec<ThreadStats>,
+}
+
+#[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+impl StatsBuffer {
+    pub fn new(num_threads: u8) -> Self {
+        Self {
+            #[cfg(feature = "stats")]
+            inner: (0..num_threads).map(|_| ThreadStats::default()).collect(),
+        }
+    }
+
+    /// Whether the engine was built with the `stats` feature
+    pub const fn enabled() -> bool {
+        cfg!(feature = "stats")
+    }
+
+    #[inline(always)]
+    pub fn node(&self, thread_id: u8, quiescence: bool) {
+        #[cfg(feature = "stats")]
+        {
+            let stats = &self.inner[thread_id as usize];
+            increment(&stats.nodes, 1);
+            increment(&stats.quiescence_nodes, quiescence as u64);
+        }
+    }
+
+    #[inline(always)]
+    pub fn tt_probe(&self, thread_id: u8, hit: bool) {
+        #[cfg(feature = "stats")]
+        {
+            let stats = &self.inner[thread_id as usize];
+            increment(&stats.tt_probes, 1);
+            increment(&stats.tt_hits, hit as u64);
+        }
+    }
+
+    #[inline(always)]
+    pub fn tt_cutoff(&self, thread_id: u8) {
+        #[cfg(feature = "stats")]
+        increment(&self.inner[thread_id as usize].tt_cutoffs, 1);
+    }
+
+    /// A move failed high, `move_number` counts from one
+    #[inline(always)]
+    pub fn beta_cutoff(&self, thread_id: u8, move_number: usize) {
+        #[cfg(feature = "stats")]
+        {
+            let stats = &self.inner[thread_id as usize];
+            increment(&stats.beta_cutoffs, 1);
+            increment(&stats.first_move_cutoffs, (move_number == 1) as u64);
+            increment(&stats.cutoff_move_number_sum, move_number as u64);
+        }
+    }
+
+    /// Statistics summed over all threads
+    pub fn accumulate(&self) -> SearchStats {
+        #[allow(unused_mut)]
+        let mut total = SearchStats::default();
+        #[cfg(feature = "stats")]
+        for stats in &self.inner {
+            total += stats.snapshot();
+        }
+        total
+    }
+
+    pub fn clear(&self) {
+        #[cfg(feature = "stats")]
+        for stats in &self.inner {
+            stats.clear();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "stats"))]
+mod test {
+    use crate::stats::StatsBuffer;
+
+    #[test]
+    fn test_accumulate_threads() {
+        let stats = StatsBuffer::new(2);
+        stats.node(0, false);
+        stats.node(1, true);
+        stats.tt_probe(0, true);
+        stats.tt_probe(1, false);
+        stats.beta_cutoff(0, 1);
+        stats.beta_cutoff(1, 3);
+
+        let total = stats.accumulate();
+        assert_eq!(total.nodes, 2);
+        assert_eq!(total.quiescence_nodes, 1);
+        assert_eq!(total.tt_hits, 1);
+        assert_eq!(total.first_move_cutoffs, 1);
+        assert_eq!(total.cutoff_move_number_sum, 4);
+
+        stats.clear();
+        assert_eq!(stats.accumulate(), Default::default());
+    }
+}
@@ -11,9 +11,9 @@ use crate::{
     ThreadSpawner,
     board::Board,
     clock::Clock,
-    evaluation::Evaluation,
-    pv::PrincipleVariation,
-    search::{NodeCountBuffer, RootMove, Search, ThreadData},
+    search::{NodeCountBuffer, RootMove, Search, ThreadData, ThreadResults},
+    skill::Skill,
+    stats::StatsBuffer,
     transposition::MaybeUninitTT,
     types::search_limits::{SearchLimits, TimeLimit},
     uci::EngineMessage,
@@ -28,6 +28,12 @@ pub struct StopSync {
     pub stop: AtomicBool,
     pub wait_for_stop: Mutex<bool>,
     pub cond_var: Condvar,
+    /// Set once the opponent played the expected move during a ponder search.
+    ///
+    /// Like `stop`, this flag is reset before each search.
+    pub ponderhit: AtomicBool,
+    /// The clock the search should switch to after a `ponderhit`
+    pub ponderhit_clock: Mutex<Option<Clock>>,
 }
 
 pub struct ThreadPool<S: ThreadSpawner> {
@@ -43,6 +49,8 @@ impl<S: ThreadSpawner> ThreadPool<S> {
         let stop_sync = Arc::new(StopSync::default());
         let barrier = Arc::new(Barrier::new(num_threads as usize));
         let nodes_buffer = Arc::new(NodeCountBuffer::new(num_threads));
+        let results = Arc::new(ThreadResults::new(num_threads));
+        let stats = Arc::new(StatsBuffer::new(num_threads));
 
         for id in 0..num_threads {
             workers.push(Self::spawn_worker(
@@ -53,6 +61,8 @@ impl<S: ThreadSpawner> ThreadPool<S> {
                 engine_tx.clone(),
                 tt.clone(),
                 nodes_buffer.clone(),
+                results.clone(),
+                stats.clone(),
                 true,
             ));
         }
@@ -64,7 +74,15 @@ impl<S: ThreadSpawner> ThreadPool<S> {
         }
     }
 
-    pub fn search(&self, board: Board, limits: SearchLimits, clock: Clock, multipv: u8) {
+    pub fn search(
+        &self,
+        board: Board,
+        limits: SearchLimits,
+        clock: Clock,
+        multipv: u8,
+        skill: Skill,
+        contempt: i16,
+    ) {
         let legal_moves = board.generate_moves();
         let root_moves = if !limits.search_moves.is_empty() {
             limits
@@ -77,29 +95,39 @@ impl<S: ThreadSpawner> ThreadPool<S> {
             legal_moves
         };
 
-        let root_moves: Vec<RootMove> = root_moves
-            .into_iter()
-            .map(|m| RootMove {
-                score: Evaluation::MIN,
-                pv: PrincipleVariation::from_root(m),
-            })
-            .collect();
+        let root_moves: Vec<RootMove> = root_moves.into_iter().map(RootMove::new).collect();
 
         // assign workers search job
         for worker in &self.workers {
             worker
                 .worker_tx
-                .send(Job::Search(Search::new(
+                .send(Job::Search(Box::new(Search::new(
                     board.clone(),
                     limits.clone(),
                     clock,
                     root_moves.clone(),
-                    multipv.min(root_moves.len().min(u8::MAX as usize) as u8),
-                )))
+                    skill
+                        .multipv(multipv)
+                        .min(root_moves.len().min(u8::MAX as usize) as u8),
+                    skill,
+                    contempt,
+                ))))
                 .unwrap();
         }
     }
 
+    /// Switches a running ponder search over to `clock`
+    pub fn ponderhit(&self, clock: Clock) {
+        *self.stop_sync.ponderhit_clock.lock().unwrap() = Some(clock);
+        self.stop_sync.ponderhit.store(true, Ordering::SeqCst);
+
+        // a ponder search that already finished can report its best move right away
+        let mut wait_for_stop = self.stop_sync.wait_for_stop.lock().unwrap();
+        *wait_for_stop = false;
+        drop(wait_for_stop);
+        self.stop_sync.cond_var.notify_all();
+    }
+
     pub fn stop_search(&self) {
         let mut wait_for_stop = self.stop_sync.wait_for_stop.lock().unwrap();
         *wait_for_stop = false;
@@ -112,6 +140,8 @@ impl<S: ThreadSpawner> ThreadPool<S> {
     pub fn resize(&mut self, num_threads: u8, engine_tx: Sender<EngineMessage>, tt: MaybeUninitTT) {
         let new_barrier = Arc::new(Barrier::new(num_threads as usize));
         let new_nodes_buffer = Arc::new(NodeCountBuffer::new(num_threads));
+        let new_results = Arc::new(ThreadResults::new(num_threads));
+        let new_stats = Arc::new(StatsBuffer::new(num_threads));
 
         for worker in &self.workers {
             worker
@@ -120,6 +150,8 @@ impl<S: ThreadSpawner> ThreadPool<S> {
                     new_num_threads: num_threads,
                     new_barrier: new_barrier.clone(),
                     new_nodes_buffer: new_nodes_buffer.clone(),
+                    new_results: new_results.clone(),
+                    new_stats: new_stats.clone(),
                 })
                 .unwrap();
         }
@@ -134,6 +166,8 @@ impl<S: ThreadSpawner> ThreadPool<S> {
                 engine_tx.clone(),
                 tt.clone(),
                 new_nodes_buffer.clone(),
+                new_results.clone(),
+                new_stats.clone(),
                 false,
             );
 
@@ -158,6 +192,13 @@ impl<S: ThreadSpawner> ThreadPool<S> {
         }
     }
 
+    /// Clears the transposition table but keeps the history tables
+    pub fn clear_tt(&self) {
+        for worker in &self.workers {
+            worker.worker_tx.send(Job::ClearTT).unwrap();
+        }
+    }
+
     pub fn quit(&self, stop_search: bool) {
         let active_threads = Arc::new(AtomicU8::new(self.workers.len() as u8));
 
@@ -181,6 +222,7 @@ impl<S: ThreadSpawner> ThreadPool<S> {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn spawn_worker(
         stop_sync: Arc<StopSync>,
         barrier: Arc<Barrier>,
@@ -189,6 +231,8 @@ impl<S: ThreadSpawner> ThreadPool<S> {
         engine_tx: Sender<EngineMessage>,
         tt: MaybeUninitTT,
         nodes_buffer: Arc<NodeCountBuffer>,
+        results: Arc<ThreadResults>,
+        stats: Arc<StatsBuffer>,
         clear_data: bool,
     ) -> WorkerHandle {
         let (worker_tx, worker_rx) = channel();
@@ -207,7 +251,11 @@ impl<S: ThreadSpawner> ThreadPool<S> {
                 tt,
                 stop_sync: stop_sync.clone(),
                 nodes_buffer,
+                results,
+                stats,
                 thread_id,
+                ordering: Default::default(),
+                correction: Default::default(),
             };
 
             worker_loop(
@@ -239,12 +287,18 @@ fn worker_loop(
             Job::Search(search) => {
                 if wait.is_leader() {
                     thread_data.nodes_buffer.clear();
+                    thread_data.results.clear();
+                    thread_data.stats.clear();
 
                     stop_sync.stop.store(false, Ordering::SeqCst);
+                    stop_sync.ponderhit.store(false, Ordering::SeqCst);
 
                     let mut wait_for_stop = stop_sync.wait_for_stop.lock().unwrap();
                     // set to false if not infinite search or ponder
-                    *wait_for_stop = search.limits.time == TimeLimit::Infinite;
+                    *wait_for_stop = matches!(
+                        search.limits.time,
+                        TimeLimit::Infinite | TimeLimit::Ponder { .. }
+                    );
                     drop(wait_for_stop);
                 }
 
@@ -255,12 +309,28 @@ fn worker_loop(
                 new_num_threads,
                 new_barrier,
                 new_nodes_buffer,
+                new_results,
+                new_stats,
             } => {
                 num_threads = new_num_threads;
                 barrier = new_barrier;
                 thread_data.nodes_buffer = new_nodes_buffer;
+                thread_data.results = new_results;
+                thread_data.stats = new_stats;
             }
             Job::ResetData => {
+                thread_data.ordering.clear();
+                thread_data.correction.clear();
+
+                // SAFETY: synchronisation and unique threads ensure that each thread
+                // has exclusive access on their respective chunk
+                unsafe {
+                    thread_data
+                        .tt
+                        .clear_chunk(thread_id as usize, num_threads as usize)
+                };
+            }
+            Job::ClearTT => {
                 // SAFETY: synchronisation and unique threads ensure that each thread
                 // has exclusive access on their respective chunk
                 unsafe {
@@ -296,16 +366,19 @@ fn worker_loop(
 }
 
 enum Job {
-    Search(Search),
+    Search(Box<Search>),
     Resize {
         new_num_threads: u8,
         new_barrier: Arc<Barrier>,
         new_nodes_buffer: Arc<NodeCountBuffer>,
+        new_results: Arc<ThreadResults>,
+        new_stats: Arc<StatsBuffer>,
     },
     Quit {
         active_threads: Arc<AtomicU8>,
     },
     ResetData,
+    ClearTT,
     Ready,
     UpdateTT {
         tt: MaybeUninitTT,
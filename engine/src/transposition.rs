@@ -35,6 +35,9 @@ impl MaybeUninitTT {
         unsafe { ptr::write_bytes(start_ptr, 0, range.len()) };
     }
 
+    /// # Safety
+    ///
+    /// Caller must ensure that every chunk has been cleared before calling this
     pub unsafe fn assume_init(self) -> TranspositionTable {
         TranspositionTable {
             inner: unsafe { self.inner.assume_init() },
@@ -74,7 +77,7 @@ impl TranspositionTable {
         }
 
         let entry = Entry {
-            hash_key: board.hash() as u16,
+            hash_key: hash_key(board),
             best_move,
             depth,
             value,
@@ -94,7 +97,7 @@ impl TranspositionTable {
         // SAFETY: we statically asserted that an entry is exactly 8 bytes
         let mut entry: Entry = unsafe { std::mem::transmute(entry) };
 
-        if entry.hash_key != board.hash() as u16 {
+        if entry.hash_key != hash_key(board) {
             return None;
         }
 
@@ -116,11 +119,33 @@ impl TranspositionTable {
         unsafe { ptr::write_bytes(start_ptr, 0, range.len()) };
     }
 
+    /// Approximate occupancy of the table in per mille, sampled from the first entries
+    pub fn hashfull(&self) -> usize {
+        const SAMPLE_SIZE: usize = 1000;
+
+        let sample = &self.inner[..SAMPLE_SIZE.min(self.inner.len())];
+        let used = sample
+            .iter()
+            .filter(|entry| entry.load(Ordering::Relaxed) != 0)
+            .count();
+
+        used * 1000 / sample.len()
+    }
+
     pub fn size_mb(&self) -> usize {
         self.inner.len() * std::mem::size_of::<AtomicU64>() / 0x100000
     }
 }
 
+/// Key stored in an entry to verify that it belongs to the probed position.
+///
+/// The index is the hash modulo the table size, which for the usual power of two sizes is made
+/// of the low bits of the hash. A key taken from the low 16 bits would then be equal for every
+/// position mapping to the same slot, so it has to come from the high bits.
+fn hash_key(board: &Board) -> u16 {
+    (board.hash() >> 48) as u16
+}
+
 fn chunk_range(len: usize, chunk_index: usize, num_chunks: usize) -> std::ops::Range<usize> {
     let stride = len / num_chunks;
     let start = stride * chunk_index;
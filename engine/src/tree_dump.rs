@@ -0,0 +1,295 @@
+//! Search tree recording for debugging, only collected with the `tree_dump` feature.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::evaluation::Evaluation;
+use crate::types::chess_move::Move;
+
+/// Why a node returned before searching all of its moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CutoffReason {
+    /// The search was stopped and the score is invalid
+    Stop,
+    /// Draw by repetition or the fifty move rule
+    Draw,
+    /// No mate shorter than an already found one is possible
+    MateDistance,
+    TranspositionTable,
+    NullMove,
+    /// The static evaluation is far above beta
+    ReverseFutility,
+    /// The static evaluation and the quiescence search are far below alpha
+    Razoring,
+    /// Even without the TT move the search fails high
+    MultiCut,
+    /// The static evaluation in the quiescence search is already good enough
+    StandPat,
+    /// A move failed high, `move_number` counts from one
+    Beta {
+        mv: Move,
+        move_number: usize,
+    },
+}
+
+impl fmt::Display for CutoffReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CutoffReason::Stop => write!(f, "stop"),
+            CutoffReason::Draw => write!(f, "draw"),
+            CutoffReason::MateDistance => write!(f, "mate distance"),
+            CutoffReason::TranspositionTable => write!(f, "tt"),
+            CutoffReason::NullMove => write!(f, "null move"),
+            CutoffReason::ReverseFutility => write!(f, "reverse futility"),
+            CutoffReason::Razoring => write!(f, "razoring"),
+            CutoffReason::MultiCut => write!(f, "multi-cut"),
+            CutoffReason::StandPat => write!(f, "stand pat"),
+            CutoffReason::Beta { mv, move_number } => write!(f, "beta {mv} #{move_number}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DumpNode {
+    parent: Option<usize>,
+    /// Move leading from the parent to this node, `None` for null moves and for nodes on the
+    /// same ply as their parent
+    mv: Option<Move>,
+    ply: u8,
+    depth: u8,
+    alpha: Evaluation,
+    beta: Evaluation,
+    quiescence: bool,
+    score: Option<Evaluation>,
+    tt_hit: bool,
+    cutoff: Option<CutoffReason>,
+}
+
+/// Records every node visited by the search for debugging a single position
+#[derive(Debug, Default)]
+pub struct TreeDump {
+    nodes: Vec<DumpNode>,
+    /// Indices of the nodes that are currently being searched
+    stack: Vec<usize>,
+}
+
+impl TreeDump {
+    /// Whether the engine was built with the `tree_dump` feature
+    pub const fn enabled() -> bool {
+        cfg!(feature = "tree_dump")
+    }
+
+    /// Opens a new node as child of the node currently being searched.
+    ///
+    /// `last_move` is the move leading to the node if it is one ply deeper than its parent.
+    pub fn enter(
+        &mut self,
+        last_move: Option<Move>,
+        ply: u8,
+        depth: u8,
+        alpha: Evaluation,
+        beta: Evaluation,
+        quiescence: bool,
+    ) {
+        let parent = self.stack.last().copied();
+        let mv = parent
+            .filter(|&parent| ply > self.nodes[parent].ply)
+            .and(last_move);
+
+        self.stack.push(self.nodes.len());
+        self.nodes.push(DumpNode {
+            parent,
+            mv,
+            ply,
+            depth,
+            alpha,
+            beta,
+            quiescence,
+            score: None,
+            tt_hit: false,
+            cutoff: None,
+        });
+    }
+
+    /// Closes the node currently being searched
+    pub fn exit(&mut self, score: Evaluation) {
+        let index = self.stack.pop().expect("exit without matching enter");
+        self.nodes[index].score = Some(score);
+    }
+
+    pub fn tt_hit(&mut self) {
+        if let Some(&index) = self.stack.last() {
+            self.nodes[index].tt_hit = true;
+        }
+    }
+
+    pub fn cutoff(&mut self, reason: CutoffReason) {
+        if let Some(&index) = self.stack.last() {
+            self.nodes[index].cutoff = Some(reason);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Moves leading from the root to the node `id`, rebuilt from the parents of the node
+    fn path_string(&self, id: usize) -> String {
+        let mut path = Vec::new();
+        let mut node = &self.nodes[id];
+        while let Some(parent) = node.parent {
+            let parent_node = &self.nodes[parent];
+            if node.ply > parent_node.ply {
+                path.push(move_string(node.mv));
+            }
+            node = parent_node;
+        }
+
+        path.reverse();
+        path.join(" ")
+    }
+
+    /// Writes one JSON object per node and line
+    pub fn write_json_lines(&self, out: &mut impl Write) -> io::Result<()> {
+        for (id, node) in self.nodes.iter().enumerate() {
+            write!(out, "{{\"id\":{id}")?;
+            match node.parent {
+                Some(parent) => write!(out, ",\"parent\":{parent}")?,
+                None => write!(out, ",\"parent\":null")?,
+            }
+            write!(out, ",\"path\":\"{}\"", self.path_string(id))?;
+            write!(
+                out,
+                ",\"ply\":{},\"depth\":{},\"alpha\":{},\"beta\":{},\"quiescence\":{}",
+                node.ply,
+                node.depth,
+                node.alpha.val(),
+                node.beta.val(),
+                node.quiescence
+            )?;
+            match node.score {
+                Some(score) => write!(out, ",\"score\":{}", score.val())?,
+                None => write!(out, ",\"score\":null")?,
+            }
+            write!(out, ",\"tt_hit\":{}", node.tt_hit)?;
+            match node.cutoff {
+                Some(cutoff) => writeln!(out, ",\"cutoff\":\"{cutoff}\"}}")?,
+                None => writeln!(out, ",\"cutoff\":null}}")?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the tree as a Graphviz digraph with the moves as edge labels
+    pub fn write_dot(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "digraph tree {{")?;
+        writeln!(out, "    node [shape=box, fontname=monospace];")?;
+
+        for (id, node) in self.nodes.iter().enumerate() {
+            let kind = if node.quiescence { "qs" } else { "d" };
+            write!(
+                out,
+                "    n{id} [label=\"{kind}{} [{}, {}]",
+                node.depth, node.alpha, node.beta
+            )?;
+            if let Some(score) = node.score {
+                write!(out, "\\nscore {score}")?;
+            }
+            if node.tt_hit {
+                write!(out, "\\ntt hit")?;
+            }
+            if let Some(cutoff) = node.cutoff {
+                write!(out, "\\n{cutoff}")?;
+            }
+            writeln!(out, "\"];")?;
+
+            if let Some(parent) = node.parent {
+                let label = if node.ply > self.nodes[parent].ply {
+                    move_string(node.mv)
+                } else if node.quiescence {
+                    "qsearch".to_owned()
+                } else {
+                    "verification".to_owned()
+                };
+                writeln!(out, "    n{parent} -> n{id} [label=\"{label}\"];")?;
+            }
+        }
+
+        writeln!(out, "}}")
+    }
+}
+
+fn move_string(mv: Option<Move>) -> String {
+    mv.map_or_else(|| "null".to_owned(), |mv| mv.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::evaluation::Evaluation;
+    use crate::tree_dump::{CutoffReason, TreeDump};
+    use crate::types::chess_move::{Move, MoveFlag};
+    use crate::types::square::Square;
+
+    fn sample_tree() -> TreeDump {
+        let e2e4 = Move::new(Square::E2, Square::E4, MoveFlag::DoublePawnPush);
+        let e7e5 = Move::new(Square::E7, Square::E5, MoveFlag::DoublePawnPush);
+
+        let mut dump = TreeDump::default();
+        dump.enter(None, 0, 2, Evaluation::MIN, Evaluation::MAX, false);
+        dump.enter(Some(e2e4), 1, 1, Evaluation::MIN, Evaluation::MAX, false);
+        dump.tt_hit();
+        dump.cutoff(CutoffReason::TranspositionTable);
+        dump.exit(Evaluation::EQUALITY);
+        dump.enter(None, 1, 1, Evaluation::MIN, Evaluation::MAX, false);
+        dump.enter(Some(e7e5), 2, 0, Evaluation::MIN, Evaluation::MAX, false);
+        dump.enter(Some(e7e5), 2, 0, Evaluation::MIN, Evaluation::MAX, true);
+        dump.exit(Evaluation::EQUALITY);
+        dump.exit(Evaluation::EQUALITY);
+        dump.exit(Evaluation::EQUALITY);
+        dump.exit(Evaluation::EQUALITY);
+        dump
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let dump = sample_tree();
+        assert_eq!(dump.len(), 5);
+
+        let mut out = Vec::new();
+        dump.write_json_lines(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].contains("\"parent\":null"));
+        assert!(lines[1].contains("\"parent\":0"));
+        assert!(lines[1].contains("\"path\":\"e2e4\""));
+        assert!(lines[1].contains("\"tt_hit\":true"));
+        assert!(lines[1].contains("\"cutoff\":\"tt\""));
+        assert!(lines[2].contains("\"path\":\"null\""));
+
+        // the path is rebuilt from the parents, nodes on the same ply do not add a move
+        assert!(lines[3].contains("\"path\":\"null e7e5\""));
+        assert!(lines[4].contains("\"parent\":3"));
+        assert!(lines[4].contains("\"path\":\"null e7e5\""));
+    }
+
+    #[test]
+    fn test_dot() {
+        let mut out = Vec::new();
+        sample_tree().write_dot(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.starts_with("digraph tree {"));
+        assert!(out.contains("n0 -> n1 [label=\"e2e4\"];"));
+        assert!(out.contains("n0 -> n2 [label=\"null\"];"));
+        assert!(out.contains("n2 -> n3 [label=\"e7e5\"];"));
+        assert!(out.contains("n3 -> n4 [label=\"qsearch\"];"));
+        assert!(out.trim_end().ends_with('}'));
+    }
+}
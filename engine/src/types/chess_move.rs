@@ -69,6 +69,11 @@ impl Move {
     pub const fn is_capture(self) -> bool {
         (self.0 >> 14) & 1 != 0
     }
+
+    /// Returns whether the move neither captures nor promotes
+    pub const fn is_quiet(self) -> bool {
+        (self.0 >> 14) == 0
+    }
 }
 
 impl fmt::Display for Move {
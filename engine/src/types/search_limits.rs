@@ -25,4 +25,9 @@ pub enum TimeLimit {
         moves_to_go: Option<u8>,
     },
     External,
+    /// Search until `ponderhit` or `stop` is received. After a `ponderhit` the search
+    /// continues with the wrapped time limit.
+    Ponder {
+        limit: Box<TimeLimit>,
+    },
 }
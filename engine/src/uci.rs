@@ -1,6 +1,7 @@
 use crate::board::Board;
-use crate::clock::Clock;
+use crate::clock::{Clock, TimeOptions};
 use crate::movegen::perf_test;
+use crate::skill::{MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO, Skill};
 use crate::threadpool::ThreadPool;
 use crate::transposition::MaybeUninitTT;
 use crate::types::color::{Color, PerColor};
@@ -22,6 +23,9 @@ const DEFAULT_THREADS: u8 = 1;
 /// Default number of principle variations
 const DEFAULT_MULTIPV: u8 = 1;
 
+/// Default penalty for draws in centipawns
+const DEFAULT_CONTEMPT: i16 = 0;
+
 #[derive(Debug)]
 pub enum EngineMessage {
     Command(String),
@@ -37,6 +41,28 @@ pub struct EngineUCI<S: ThreadSpawner, P: Printer> {
     transposition_table: MaybeUninitTT,
     ignore_commands: bool,
     multipv: u8,
+    time_options: TimeOptions,
+    skill_level: u8,
+    limit_strength: bool,
+    elo: u16,
+    /// Penalty for draws in centipawns
+    contempt: i16,
+    /// Whether contempt is also used for infinite analysis
+    analysis_contempt: bool,
+    /// Contempt from white's perspective of the searches that filled the transposition table.
+    ///
+    /// Draw scores biased by this contempt spread through the stored scores, so the table is
+    /// cleared when a search uses a different one. With the default contempt of zero this never
+    /// happens, and an engine playing one side with contempt keeps the same bias for the whole
+    /// game. The histories only order moves and are kept.
+    tt_contempt: i16,
+    /// Time limit of the running ponder search that applies once `ponderhit` is received
+    ponder_limit: Option<TimeLimit>,
+    /// Number of threads requested by the `Threads` option
+    threads: u8,
+    /// Reproduce the exact same search for the same position and limits by searching on a
+    /// single thread with cleared tables
+    deterministic: bool,
     _marker: PhantomData<P>,
 }
 
@@ -57,6 +83,9 @@ enum Command {
     Perft {
         depth: u8,
     },
+    PonderHit {
+        start_time: Instant,
+    },
     Debug,
     Stop,
     Quit,
@@ -75,7 +104,7 @@ enum ParseCommandError {
 #[derive(Debug, PartialEq)]
 enum StartingPosition {
     Standard,
-    Custom(Board),
+    Custom(Box<Board>),
 }
 
 impl<S: ThreadSpawner, P: Printer> EngineUCI<S, P> {
@@ -90,6 +119,16 @@ impl<S: ThreadSpawner, P: Printer> EngineUCI<S, P> {
             transposition_table: tt,
             ignore_commands: false,
             multipv: DEFAULT_MULTIPV,
+            time_options: TimeOptions::default(),
+            skill_level: MAX_SKILL_LEVEL,
+            limit_strength: false,
+            elo: MAX_ELO,
+            contempt: DEFAULT_CONTEMPT,
+            analysis_contempt: false,
+            tt_contempt: 0,
+            ponder_limit: None,
+            threads: DEFAULT_THREADS,
+            deterministic: false,
             _marker: Default::default(),
         }
     }
@@ -135,6 +174,9 @@ impl<S: ThreadSpawner, P: Printer> EngineUCI<S, P> {
             "quit" => Command::Quit,
             "softquit" => Command::SoftQuit,
             "stop" => Command::Stop,
+            "ponderhit" => Command::PonderHit {
+                start_time: Instant::now(),
+            },
             _ => return Err(ParseCommandError::UnknownCommand),
         };
 
@@ -159,6 +201,46 @@ impl<S: ThreadSpawner, P: Printer> EngineUCI<S, P> {
                     "option name MultiPV type spin default {DEFAULT_MULTIPV} min 1 max 255"
                 ));
 
+                P::println("option name Ponder type check default false");
+
+                P::println(&format!(
+                    "option name Move Overhead type spin default {} min 0 max 5000",
+                    TimeOptions::DEFAULT_MOVE_OVERHEAD
+                ));
+
+                P::println(&format!(
+                    "option name Slow Mover type spin default {} min 10 max 1000",
+                    TimeOptions::DEFAULT_SLOW_MOVER
+                ));
+
+                P::println(&format!(
+                    "option name Minimum Thinking Time type spin default {} min 0 max 5000",
+                    TimeOptions::DEFAULT_MINIMUM_THINKING_TIME
+                ));
+
+                P::println(&format!(
+                    "option name nodestime type spin default {} min 0 max 10000",
+                    TimeOptions::DEFAULT_NODES_TIME
+                ));
+
+                P::println(&format!(
+                    "option name Skill Level type spin default {MAX_SKILL_LEVEL} min 0 max {MAX_SKILL_LEVEL}"
+                ));
+
+                P::println("option name UCI_LimitStrength type check default false");
+
+                P::println(&format!(
+                    "option name UCI_Elo type spin default {MAX_ELO} min {MIN_ELO} max {MAX_ELO}"
+                ));
+
+                P::println(&format!(
+                    "option name Contempt type spin default {DEFAULT_CONTEMPT} min -100 max 100"
+                ));
+
+                P::println("option name Analysis Contempt type check default false");
+
+                P::println("option name Deterministic type check default false");
+
                 P::println("uciok");
             }
             Command::IsReady => {
@@ -167,11 +249,15 @@ impl<S: ThreadSpawner, P: Printer> EngineUCI<S, P> {
             Command::SetOption { name, value } => match name.as_str() {
                 "Threads" => {
                     if let Some(num_threads) = value.and_then(|v| v.parse::<u8>().ok()) {
-                        self.threadpool.resize(
-                            num_threads,
-                            self.engine_tx.clone(),
-                            self.transposition_table.clone(),
-                        );
+                        self.threads = num_threads;
+                        // a deterministic search always runs on a single thread
+                        if !self.deterministic {
+                            self.threadpool.resize(
+                                num_threads,
+                                self.engine_tx.clone(),
+                                self.transposition_table.clone(),
+                            );
+                        }
                     } else {
                         eprintln!("invalid value");
                     }
@@ -192,15 +278,81 @@ impl<S: ThreadSpawner, P: Printer> EngineUCI<S, P> {
                         eprintln!("invalid value");
                     }
                 }
+                // the GUI decides whether to ponder, there is nothing to configure
+                "Ponder" => {}
+                "Move Overhead" | "Slow Mover" | "Minimum Thinking Time" | "nodestime" => {
+                    if let Some(option_value) = value.and_then(|v| v.parse::<u64>().ok()) {
+                        let time_options = &mut self.time_options;
+                        match name.as_str() {
+                            "Move Overhead" => time_options.move_overhead = option_value.min(5000),
+                            "Slow Mover" => time_options.slow_mover = option_value.clamp(10, 1000),
+                            "Minimum Thinking Time" => {
+                                time_options.minimum_thinking_time = option_value.min(5000)
+                            }
+                            "nodestime" => time_options.nodes_time = option_value.min(10000),
+                            _ => unreachable!(),
+                        }
+                    } else {
+                        eprintln!("invalid value");
+                    }
+                }
+                "Skill Level" => {
+                    if let Some(skill_level) = value.and_then(|v| v.parse::<u8>().ok()) {
+                        self.skill_level = skill_level.min(MAX_SKILL_LEVEL);
+                    } else {
+                        eprintln!("invalid value");
+                    }
+                }
+                "UCI_LimitStrength" => {
+                    if let Some(limit_strength) = value.and_then(|v| v.parse::<bool>().ok()) {
+                        self.limit_strength = limit_strength;
+                    } else {
+                        eprintln!("invalid value");
+                    }
+                }
+                "UCI_Elo" => {
+                    if let Some(elo) = value.and_then(|v| v.parse::<u16>().ok()) {
+                        self.elo = elo.clamp(MIN_ELO, MAX_ELO);
+                    } else {
+                        eprintln!("invalid value");
+                    }
+                }
+                "Contempt" => {
+                    if let Some(contempt) = value.and_then(|v| v.parse::<i16>().ok()) {
+                        self.contempt = contempt.clamp(-100, 100);
+                    } else {
+                        eprintln!("invalid value");
+                    }
+                }
+                "Analysis Contempt" => {
+                    if let Some(analysis_contempt) = value.and_then(|v| v.parse::<bool>().ok()) {
+                        self.analysis_contempt = analysis_contempt;
+                    } else {
+                        eprintln!("invalid value");
+                    }
+                }
+                "Deterministic" => {
+                    if let Some(deterministic) = value.and_then(|v| v.parse::<bool>().ok()) {
+                        self.deterministic = deterministic;
+                        self.threadpool.resize(
+                            if deterministic { 1 } else { self.threads },
+                            self.engine_tx.clone(),
+                            self.transposition_table.clone(),
+                        );
+                    } else {
+                        eprintln!("invalid value");
+                    }
+                }
                 _ => eprintln!("invalid option"),
             },
             Command::NewGame => {
                 self.threadpool.reset_data();
+                self.tt_contempt = 0;
             }
             Command::Position(start_pos, moves) => {
                 let mut board = match start_pos {
                     StartingPosition::Standard => Board::default(),
-                    StartingPosition::Custom(board) => board,
+                    StartingPosition::Custom(board) => *board,
                 };
 
                 for uci_move in moves {
@@ -215,16 +367,65 @@ impl<S: ThreadSpawner, P: Printer> EngineUCI<S, P> {
                 self.board = board;
             }
             Command::Go { start_time, limits } => {
+                self.ponder_limit = match &limits.time {
+                    TimeLimit::Ponder { limit } => Some(limit.as_ref().clone()),
+                    _ => None,
+                };
+
                 // The clock should be started as soon as possible even if the search has to wait in queue
                 let clock = Clock::new(
                     start_time,
                     &limits.time,
                     self.board.game_ply(),
                     self.board.side_to_move(),
+                    &self.time_options,
                 );
 
-                self.threadpool
-                    .search(self.board.clone(), limits, clock, self.multipv);
+                let mut skill = Skill::new(self.skill_level, self.limit_strength, self.elo);
+
+                if self.deterministic {
+                    // start every search from the same empty tables
+                    self.threadpool.reset_data();
+                    skill = skill.deterministic();
+                }
+
+                let contempt =
+                    effective_contempt(self.contempt, self.analysis_contempt, &limits.time);
+
+                let white_contempt = match self.board.side_to_move() {
+                    Color::White => contempt,
+                    Color::Black => -contempt,
+                };
+                if white_contempt != self.tt_contempt {
+                    self.threadpool.clear_tt();
+                    self.tt_contempt = white_contempt;
+                }
+
+                self.threadpool.search(
+                    self.board.clone(),
+                    limits,
+                    clock,
+                    self.multipv,
+                    skill,
+                    contempt,
+                );
+            }
+            Command::PonderHit { start_time } => {
+                let Some(limit) = self.ponder_limit.take() else {
+                    eprintln!("not pondering");
+                    return;
+                };
+
+                // our clock only starts running once the opponent played the expected move
+                let clock = Clock::new(
+                    start_time,
+                    &limit,
+                    self.board.game_ply(),
+                    self.board.side_to_move(),
+                    &self.time_options,
+                );
+
+                self.threadpool.ponderhit(clock);
             }
             Command::Debug => {
                 P::println(self.board.to_string().as_str());
@@ -247,6 +448,16 @@ impl<S: ThreadSpawner, P: Printer> EngineUCI<S, P> {
     }
 }
 
+/// Contempt used by a search with the time limit `time`. Infinite analysis keeps the
+/// evaluation neutral unless `analysis_contempt` is set.
+fn effective_contempt(contempt: i16, analysis_contempt: bool, time: &TimeLimit) -> i16 {
+    if *time == TimeLimit::Infinite && !analysis_contempt {
+        0
+    } else {
+        contempt
+    }
+}
+
 fn parse_perft(
     mut parts: Peekable<SplitAsciiWhitespace<'_>>,
 ) -> Result<Command, ParseCommandError> {
@@ -310,12 +521,16 @@ fn parse_go(mut parts: Peekable<SplitAsciiWhitespace<'_>>) -> Result<Command, Pa
     let mut moves_to_go: Option<u8> = None;
     let mut nodes: Option<u64> = None;
     let mut infinite = false;
+    let mut ponder = false;
     let mut search_moves = vec![];
     while let Some(token) = parts.next() {
         match token {
             "infinite" => {
                 infinite = true;
             }
+            "ponder" => {
+                ponder = true;
+            }
             "wtime" | "btime" | "winc" | "binc" | "movetime" => {
                 let param = parts
                     .next()
@@ -380,6 +595,14 @@ fn parse_go(mut parts: Peekable<SplitAsciiWhitespace<'_>>) -> Result<Command, Pa
         TimeLimit::External
     };
 
+    let time = if ponder {
+        TimeLimit::Ponder {
+            limit: Box::new(time),
+        }
+    } else {
+        time
+    };
+
     let limits = SearchLimits {
         time,
         depth,
@@ -408,9 +631,9 @@ fn parse_position(
                 .collect::<Vec<_>>()
                 .join(" ");
 
-            starting_pos = StartingPosition::Custom(
+            starting_pos = StartingPosition::Custom(Box::new(
                 Board::from_str(fen.as_str()).map_err(|_| ParseCommandError::InvalidStartingPos)?,
-            );
+            ));
         }
         _ => return Err(ParseCommandError::MissingParts),
     }
@@ -420,3 +643,133 @@ fn parse_position(
         .map_err(|_| ParseCommandError::InvalidMove)?;
     Ok(Command::Position(starting_pos, moves))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    struct TestSpawner;
+    impl ThreadSpawner for TestSpawner {
+        fn spawn<F>(f: F)
+        where
+            F: FnOnce() + Send + 'static,
+        {
+            std::thread::spawn(f);
+        }
+    }
+
+    struct TestPrinter;
+    impl Printer for TestPrinter {
+        fn println(_: &str) {}
+    }
+
+    fn parse_go_limits(args: &str) -> SearchLimits {
+        match parse_go(args.split_ascii_whitespace().peekable()) {
+            Ok(Command::Go { limits, .. }) => limits,
+            other => panic!("unexpected parse result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_go_ponder() {
+        let ponder = |limit| TimeLimit::Ponder {
+            limit: Box::new(limit),
+        };
+
+        let mut time_left = PerColor::default();
+        time_left[Color::White] = Duration::from_millis(1000);
+        time_left[Color::Black] = Duration::from_millis(2000);
+        let mut increment = PerColor::default();
+        increment[Color::White] = Duration::from_millis(10);
+        increment[Color::Black] = Duration::from_millis(20);
+        let dynamic = TimeLimit::Dynamic {
+            time_left,
+            increment,
+            moves_to_go: None,
+        };
+
+        assert_eq!(
+            parse_go_limits("ponder wtime 1000 btime 2000 winc 10 binc 20").time,
+            ponder(dynamic.clone())
+        );
+        assert_eq!(
+            parse_go_limits("wtime 1000 btime 2000 winc 10 binc 20 ponder").time,
+            ponder(dynamic.clone())
+        );
+        assert_eq!(
+            parse_go_limits("wtime 1000 btime 2000 winc 10 binc 20").time,
+            dynamic
+        );
+        assert_eq!(
+            parse_go_limits("ponder movetime 500").time,
+            ponder(TimeLimit::Fixed {
+                move_time: Duration::from_millis(500)
+            })
+        );
+        assert_eq!(
+            parse_go_limits("ponder infinite").time,
+            ponder(TimeLimit::Infinite)
+        );
+        assert_eq!(parse_go_limits("ponder").time, ponder(TimeLimit::External));
+
+        let limits = parse_go_limits("ponder depth 5");
+        assert_eq!(limits.time, ponder(TimeLimit::External));
+        assert_eq!(limits.depth, Some(5));
+    }
+
+    #[test]
+    fn test_time_options_are_clamped() {
+        let (engine_tx, _engine_rx) = channel();
+        let mut engine = EngineUCI::<TestSpawner, TestPrinter>::new(engine_tx);
+
+        engine.receive_command("setoption name Move Overhead value 18446744073709551615");
+        engine.receive_command("setoption name Slow Mover value 18446744073709551615");
+        engine.receive_command("setoption name Minimum Thinking Time value 100000");
+        engine.receive_command("setoption name nodestime value 100000");
+        assert_eq!(
+            engine.time_options,
+            TimeOptions {
+                move_overhead: 5000,
+                slow_mover: 1000,
+                minimum_thinking_time: 5000,
+                nodes_time: 10000,
+            }
+        );
+
+        engine.receive_command("setoption name Slow Mover value 0");
+        assert_eq!(engine.time_options.slow_mover, 10);
+    }
+
+    #[test]
+    fn test_ponderhit_without_ponder_search() {
+        let (engine_tx, engine_rx) = channel();
+        let mut engine = EngineUCI::<TestSpawner, TestPrinter>::new(engine_tx);
+
+        assert!(matches!(
+            engine.parse_command("ponderhit"),
+            Ok(Command::PonderHit { .. })
+        ));
+
+        // without a running ponder search the command is ignored
+        engine.receive_command("ponderhit");
+        assert_eq!(engine.ponder_limit, None);
+        assert!(engine_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_effective_contempt() {
+        let timed = TimeLimit::Fixed {
+            move_time: Duration::from_millis(100),
+        };
+        let ponder = TimeLimit::Ponder {
+            limit: Box::new(timed.clone()),
+        };
+
+        assert_eq!(effective_contempt(20, false, &timed), 20);
+        assert_eq!(effective_contempt(20, false, &ponder), 20);
+        assert_eq!(effective_contempt(20, false, &TimeLimit::Infinite), 0);
+        assert_eq!(effective_contempt(20, true, &TimeLimit::Infinite), 20);
+        assert_eq!(effective_contempt(-20, true, &TimeLimit::Infinite), -20);
+    }
+}
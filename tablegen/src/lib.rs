@@ -8,6 +8,7 @@ pub mod knight_move;
 pub mod magics;
 pub mod pawn_move;
 pub mod rays_between;
+pub mod reductions;
 pub mod slider_move;
 pub mod zobrist;
 
@@ -3,10 +3,15 @@ use crate::{BitBoard, Color, Square};
 pub fn generate_pawn_attacks() -> [[BitBoard; 64]; 2] {
     let mut result = [[BitBoard(0); 64]; 2];
 
-    for square in 0..64 {
-        let sq = Square::from_index(square as u8);
-        result[Color::White as usize][square] = mask_pawn_attacks(sq, Color::White);
-        result[Color::Black as usize][square] = mask_pawn_attacks(sq, Color::Black);
+    for (color, attacks) in result.iter_mut().enumerate() {
+        let side = if color == Color::White as usize {
+            Color::White
+        } else {
+            Color::Black
+        };
+        for (square, attack) in attacks.iter_mut().enumerate() {
+            *attack = mask_pawn_attacks(Square::from_index(square as u8), side);
+        }
     }
 
     result
@@ -0,0 +1,44 @@
+/// Upper bound (exclusive) for the depth and move index of the reduction table
+pub const MAX_REDUCTION_INDEX: usize = 64;
+
+/// Generates the late move reduction table indexed by remaining depth and move index.
+///
+/// The reduction grows logarithmically in both the depth and the number of moves
+/// already searched at the node.
+pub fn generate_lmr_reductions() -> [[u8; MAX_REDUCTION_INDEX]; MAX_REDUCTION_INDEX] {
+    let mut result = [[0u8; MAX_REDUCTION_INDEX]; MAX_REDUCTION_INDEX];
+
+    for (depth, reductions) in result.iter_mut().enumerate().skip(1) {
+        for (move_index, reduction) in reductions.iter_mut().enumerate().skip(1) {
+            let value = 0.75 + (depth as f64).ln() * (move_index as f64).ln() / 2.25;
+            *reduction = value as u8;
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reductions_are_monotonic() {
+        let reductions = generate_lmr_reductions();
+
+        for depth in 1..MAX_REDUCTION_INDEX {
+            for move_index in 1..MAX_REDUCTION_INDEX {
+                let reduction = reductions[depth][move_index];
+                assert!(reduction >= reductions[depth - 1][move_index]);
+                assert!(reduction >= reductions[depth][move_index - 1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_reduction_at_origin() {
+        let reductions = generate_lmr_reductions();
+        assert_eq!(reductions[0][0], 0);
+        assert_eq!(reductions[1][1], 0);
+    }
+}